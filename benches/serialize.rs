@@ -0,0 +1,115 @@
+//! Serializing indexed rows: building JSON values with `Decoder` first (`values`) against
+//! writing them straight from the driver rows with `IndexedRows` (`streamed`). Criterion
+//! measures throughput; the allocations each path makes are counted by the global
+//! allocator and printed first.
+//!
+//! Runs against `DATABASE_URL`, an in-memory SQLite database by default:
+//! `cargo bench --bench serialize`.
+
+use std::alloc::{GlobalAlloc, Layout as AllocLayout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use sandworm_api::encoding::{IndexedRows, Layout};
+use sandworm_api::sql_to_json::{DecodeOptions, Decoder};
+use sqlx::any::AnyRow;
+use sqlx::Connection;
+
+const ROWS: usize = 10_000;
+
+struct Counting;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+static ALLOCATED_BYTES: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: AllocLayout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        ALLOCATED_BYTES.fetch_add(layout.size(), Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: AllocLayout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: AllocLayout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        ALLOCATED_BYTES.fetch_add(new_size, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+fn fetch_rows() -> Vec<AnyRow> {
+    let db_url = std::env::var("DATABASE_URL").unwrap_or_else(|_| "sqlite::memory:".to_string());
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    runtime.block_on(async {
+        let mut c = sqlx::AnyConnection::connect(&db_url).await.unwrap();
+        let sql = format!(
+            "WITH RECURSIVE seq(n) AS (SELECT 1 UNION ALL SELECT n + 1 FROM seq WHERE n < {ROWS}) \
+             SELECT n AS id, 'account ' || n AS name, n * 1.5 AS balance, \
+             CAST(printf('%032x', n) AS BLOB) AS hash, n % 2 = 0 AS even FROM seq"
+        );
+        sqlx::query(&sql).fetch_all(&mut c).await.unwrap()
+    })
+}
+
+fn values(rows: &[AnyRow], layout: Layout) -> Vec<u8> {
+    let mut decoder = Decoder::new(DecodeOptions::default());
+    let rows = match layout {
+        Layout::Objects => decoder.objects(rows),
+        Layout::Arrays => decoder.arrays(rows),
+        Layout::ColumnArrays => decoder.column_arrays(rows),
+    };
+    serde_json::to_vec(&rows).unwrap()
+}
+
+fn streamed(rows: &[AnyRow], layout: Layout) -> Vec<u8> {
+    serde_json::to_vec(&IndexedRows::new(rows, layout, DecodeOptions::default())).unwrap()
+}
+
+type Encode = fn(&[AnyRow], Layout) -> Vec<u8>;
+
+const PATHS: [(&str, Encode); 2] = [("values", values), ("streamed", streamed)];
+const LAYOUTS: [(&str, Layout); 2] = [("objects", Layout::Objects), ("arrays", Layout::Arrays)];
+
+fn report_allocations(rows: &[AnyRow]) {
+    println!("allocations for {} rows:", rows.len());
+    for (layout_name, layout) in LAYOUTS {
+        for (path, encode) in PATHS {
+            let (count, bytes) = (
+                ALLOCATIONS.load(Ordering::Relaxed),
+                ALLOCATED_BYTES.load(Ordering::Relaxed),
+            );
+            let body = encode(rows, layout);
+            let count = ALLOCATIONS.load(Ordering::Relaxed) - count;
+            let bytes = ALLOCATED_BYTES.load(Ordering::Relaxed) - bytes;
+            println!(
+                "  {layout_name}/{path}: {count} allocations, {bytes} bytes allocated, {} byte body",
+                body.len()
+            );
+        }
+    }
+}
+
+fn serialize(c: &mut Criterion) {
+    let rows = fetch_rows();
+    report_allocations(&rows);
+
+    let mut group = c.benchmark_group("serialize");
+    group.throughput(Throughput::Elements(rows.len() as u64));
+    for (layout_name, layout) in LAYOUTS {
+        for (path, encode) in PATHS {
+            group.bench_with_input(BenchmarkId::new(path, layout_name), &layout, |b, layout| {
+                b.iter(|| encode(&rows, *layout))
+            });
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, serialize);
+criterion_main!(benches);
@@ -0,0 +1,770 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use ethabi::ethereum_types::{H256, U256};
+use ethabi::{Contract, RawLog, Token};
+use serde::{Deserialize, Serialize, Serializer};
+use serde_json::value::RawValue;
+use serde_json::{Map, Number, Value};
+use sqlx::any::{AnyKind, AnyPool, AnyRow};
+use sqlx::{Column as _, Decode, Row, ValueRef};
+use tokio::sync::RwLock;
+use utoipa::ToSchema;
+
+use crate::encoding::AppendedColumn;
+use crate::response::Column;
+use crate::sql_to_json::{
+    exact_number, is_binary, suffixed_names, try_sql_to_json, BigIntFormat, DecodeOptions,
+};
+
+/// Server-owned table holding registered ABIs. It has no chain prefix, so it is never
+/// exposed as a chain table, and the query blacklist rejects its name.
+pub const ABI_TABLE: &str = "sandworm_abis";
+
+const DEFAULT_ABI_CACHE_TTL_SECS: u64 = 60;
+
+/// Postgres schema holding [`ABI_TABLE`], apart from the tables users query.
+const DEFAULT_ABI_SCHEMA: &str = "sandworm_internal";
+
+/// Columns read to find the contract a row belongs to, in order of preference: `address`
+/// for logs, the call target for transactions.
+const ADDRESS_COLUMNS: &[&str] = &["address", "to_address", "to"];
+
+/// Parsed ABIs keyed by chain id and lowercase `0x` address.
+pub type Contracts = HashMap<(String, String), Contract>;
+
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema)]
+pub struct AbiRegistration {
+    /// Chain id or alias, e.g. `eth`.
+    pub chain: String,
+    /// Contract address, `0x` followed by 40 hex digits.
+    pub address: String,
+    /// The contract's JSON ABI, as emitted by the Solidity compiler.
+    #[schema(value_type = Vec<Object>)]
+    pub abi: Value,
+}
+
+struct CachedAbis {
+    loaded_at: Instant,
+    contracts: Arc<Contracts>,
+}
+
+/// Contract ABIs registered for `decode=abi`, stored in [`ABI_TABLE`] and cached for `ttl`.
+///
+/// Only Postgres and SQLite are supported: the statements use `$n` placeholders and
+/// `ON CONFLICT`. On Postgres the table lives in `schema`; SQLite has no schemas.
+pub struct AbiRegistry {
+    ttl: Duration,
+    schema: String,
+    cached: RwLock<Option<CachedAbis>>,
+}
+
+impl AbiRegistry {
+    pub fn new(ttl: Duration, schema: String) -> Self {
+        AbiRegistry {
+            ttl,
+            schema,
+            cached: RwLock::new(None),
+        }
+    }
+
+    /// Reads `ABI_CACHE_TTL_SECS` (default 60) and `ABI_DATABASE_SCHEMA` (default
+    /// `sandworm_internal`).
+    pub fn from_env() -> Self {
+        let ttl = std::env::var("ABI_CACHE_TTL_SECS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_ABI_CACHE_TTL_SECS);
+        let schema = std::env::var("ABI_DATABASE_SCHEMA")
+            .ok()
+            .filter(|schema| {
+                let valid = !schema.is_empty()
+                    && schema
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || c == '_');
+                if !valid {
+                    log::warn!("Ignoring invalid ABI_DATABASE_SCHEMA '{schema}'");
+                }
+                valid
+            })
+            .unwrap_or_else(|| DEFAULT_ABI_SCHEMA.to_string());
+        Self::new(Duration::from_secs(ttl), schema)
+    }
+
+    /// The registry table's qualified name on this backend.
+    fn table(&self, pool: &AnyPool) -> Result<String, sqlx::Error> {
+        match pool.any_kind() {
+            AnyKind::Postgres => Ok(format!("{}.{ABI_TABLE}", self.schema)),
+            AnyKind::Sqlite => Ok(ABI_TABLE.to_string()),
+            kind => Err(sqlx::Error::Configuration(
+                format!("The ABI registry needs Postgres or SQLite, not {kind:?}").into(),
+            )),
+        }
+    }
+
+    pub async fn create_table(&self, pool: &AnyPool) -> Result<(), sqlx::Error> {
+        let table = self.table(pool)?;
+        if pool.any_kind() == AnyKind::Postgres {
+            sqlx::query(&format!("CREATE SCHEMA IF NOT EXISTS {}", self.schema))
+                .execute(pool)
+                .await?;
+        }
+        sqlx::query(&format!(
+            "CREATE TABLE IF NOT EXISTS {table} (\
+             chain TEXT NOT NULL, address TEXT NOT NULL, abi TEXT NOT NULL, \
+             PRIMARY KEY (chain, address))"
+        ))
+        .execute(pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Stores or replaces the ABI for `chain` and `address`, which must already be
+    /// canonical (see [`normalize_address`]).
+    pub async fn register(
+        &self,
+        pool: &AnyPool,
+        chain: &str,
+        address: &str,
+        abi: &Value,
+    ) -> Result<(), sqlx::Error> {
+        let table = self.table(pool)?;
+        sqlx::query(&format!(
+            "INSERT INTO {table} (chain, address, abi) VALUES ($1, $2, $3) \
+             ON CONFLICT (chain, address) DO UPDATE SET abi = excluded.abi"
+        ))
+        .bind(chain)
+        .bind(address)
+        .bind(abi.to_string())
+        .execute(pool)
+        .await?;
+        *self.cached.write().await = None;
+        Ok(())
+    }
+
+    /// The ABI registered for `chain` and `address`, if any.
+    pub async fn get(
+        &self,
+        pool: &AnyPool,
+        chain: &str,
+        address: &str,
+    ) -> Result<Option<Value>, sqlx::Error> {
+        let table = self.table(pool)?;
+        let row = sqlx::query(&format!(
+            "SELECT abi FROM {table} WHERE chain = $1 AND address = $2"
+        ))
+        .bind(chain)
+        .bind(address)
+        .fetch_optional(pool)
+        .await?;
+        let Some(row) = row else {
+            return Ok(None);
+        };
+        let abi: String = row.try_get("abi")?;
+        serde_json::from_str(&abi)
+            .map(Some)
+            .map_err(|e| sqlx::Error::Decode(Box::new(e)))
+    }
+
+    /// Every registered contract, reloaded once the cache is older than `ttl`.
+    pub async fn contracts(&self, pool: &AnyPool) -> Result<Arc<Contracts>, sqlx::Error> {
+        {
+            let cached = self.cached.read().await;
+            if let Some(cached) = cached.as_ref().filter(|c| c.loaded_at.elapsed() < self.ttl) {
+                return Ok(cached.contracts.clone());
+            }
+        }
+
+        let mut cached = self.cached.write().await;
+        let table = self.table(pool)?;
+        let rows = sqlx::query(&format!("SELECT chain, address, abi FROM {table}"))
+            .fetch_all(pool)
+            .await?;
+        let mut contracts = Contracts::new();
+        for row in rows {
+            let chain: String = row.try_get("chain")?;
+            let address: String = row.try_get("address")?;
+            let abi: String = row.try_get("abi")?;
+            match serde_json::from_str::<Contract>(&abi) {
+                Ok(contract) => {
+                    contracts.insert((chain, address), contract);
+                }
+                Err(e) => log::warn!("Skipping invalid ABI for {chain} {address}: {e}"),
+            }
+        }
+        log::info!("Loaded {} contract ABIs", contracts.len());
+        let contracts = Arc::new(contracts);
+        *cached = Some(CachedAbis {
+            loaded_at: Instant::now(),
+            contracts: contracts.clone(),
+        });
+        Ok(contracts)
+    }
+}
+
+/// Lowercase `0x` form of a contract address.
+pub fn normalize_address(address: &str) -> Result<String, String> {
+    match parse_hex(address) {
+        Some(bytes) if bytes.len() == 20 => Ok(format!("0x{}", hex::encode(bytes))),
+        _ => Err(format!(
+            "Invalid address '{address}': expected 0x followed by 40 hex digits"
+        )),
+    }
+}
+
+/// Parses a contract's JSON ABI.
+pub fn parse_abi(abi: &Value) -> Result<Contract, String> {
+    Contract::deserialize(abi).map_err(|e| format!("Invalid ABI: {e}"))
+}
+
+/// The `event_name`, `function_name` and `args` columns `decode=abi` appends to indexed
+/// results, named so they do not clash with `taken`.
+///
+/// Rows with `topics` (or `topic0`..`topic3`) and `data` are decoded as logs of the
+/// contract at `address`; rows with `input` as calls to the contract at `to_address`/`to`.
+/// Contracts are looked up on each of `chains` in turn. Rows that match no registered
+/// event or function get nulls.
+pub fn decode_rows(
+    rows: &[AnyRow],
+    chains: &[&str],
+    contracts: &Contracts,
+    taken: &[String],
+    options: &DecodeOptions,
+) -> (Vec<Column>, Vec<AppendedColumn>) {
+    let mut event_names = Vec::with_capacity(rows.len());
+    let mut function_names = Vec::with_capacity(rows.len());
+    let mut args = Vec::with_capacity(rows.len());
+    let mut exact_args = Vec::with_capacity(rows.len());
+    for row in rows {
+        let contract = address(row, options).and_then(|address| {
+            chains
+                .iter()
+                .find_map(|chain| contracts.get(&(chain.to_string(), address.clone())))
+        });
+        let event = contract.and_then(|contract| {
+            let topics = topics(row, options)?;
+            let data = column_bytes(row, "data", options).unwrap_or_default();
+            decode_event(contract, &topics, &data)
+        });
+        let call = match (contract, &event) {
+            (Some(contract), None) => {
+                column_bytes(row, "input", options).and_then(|input| decode_call(contract, &input))
+            }
+            _ => None,
+        };
+        let (event_name, function_name, decoded) = match (event, call) {
+            (Some((name, decoded)), _) => (Value::String(name), Value::Null, Some(decoded)),
+            (None, Some((name, decoded))) => (Value::Null, Value::String(name), Some(decoded)),
+            (None, None) => (Value::Null, Value::Null, None),
+        };
+        event_names.push(event_name);
+        function_names.push(function_name);
+        args.push(
+            decoded
+                .as_ref()
+                .map_or(Value::Null, |args| args.to_json(options)),
+        );
+        exact_args.push(decoded.and_then(|args| args.exact_json(options)));
+    }
+
+    let mut names: Vec<&str> = taken.iter().map(String::as_str).collect();
+    names.extend(["event_name", "function_name", "args"]);
+    let names = suffixed_names(names.iter().copied());
+    let names = &names[taken.len()..];
+    let columns = [
+        ("text", &names[0]),
+        ("text", &names[1]),
+        ("json", &names[2]),
+    ]
+    .into_iter()
+    .map(|(type_name, name)| Column {
+        name: name.clone(),
+        type_name: type_name.to_string(),
+        logical_type: type_name.to_string(),
+        nullable: Some(true),
+        unsupported: false,
+    })
+    .collect();
+    let appended = names
+        .iter()
+        .zip([event_names, function_names, args])
+        .zip([Vec::new(), Vec::new(), exact_args])
+        .map(|((name, values), json)| AppendedColumn {
+            name: name.clone(),
+            values,
+            json,
+        })
+        .collect();
+    (columns, appended)
+}
+
+/// The event `topics[0]` names, with its parameters by name.
+pub fn decode_event(
+    contract: &Contract,
+    topics: &[H256],
+    data: &[u8],
+) -> Option<(String, DecodedArgs)> {
+    let topic0 = topics.first()?;
+    let event = contract
+        .events()
+        .find(|event| !event.anonymous && event.signature() == *topic0)?;
+    let log = event
+        .parse_log(RawLog {
+            topics: topics.to_vec(),
+            data: data.to_vec(),
+        })
+        .map_err(|e| log::debug!("Could not decode {} log: {e}", event.name))
+        .ok()?;
+    let args = log
+        .params
+        .into_iter()
+        .enumerate()
+        .map(|(index, param)| (arg_name(param.name, index), param.value))
+        .collect();
+    Some((event.name.clone(), DecodedArgs(args)))
+}
+
+/// The function the first four bytes of `input` select, with its arguments by name.
+pub fn decode_call(contract: &Contract, input: &[u8]) -> Option<(String, DecodedArgs)> {
+    let (selector, arguments) = (input.get(..4)?, &input[4..]);
+    let function = contract
+        .functions()
+        .find(|function| function.short_signature().as_slice() == selector)?;
+    let tokens = function
+        .decode_input(arguments)
+        .map_err(|e| log::debug!("Could not decode {} call: {e}", function.name))
+        .ok()?;
+    let args = function
+        .inputs
+        .iter()
+        .zip(tokens)
+        .enumerate()
+        .map(|(index, (param, token))| (arg_name(param.name.clone(), index), token))
+        .collect();
+    Some((function.name.clone(), DecodedArgs(args)))
+}
+
+/// Unnamed parameters are keyed by position, `arg0`, `arg1`, ...
+fn arg_name(name: String, index: usize) -> String {
+    if name.is_empty() {
+        format!("arg{index}")
+    } else {
+        name
+    }
+}
+
+/// Decoded event or call parameters by name, in declaration order.
+#[derive(Debug, Clone)]
+pub struct DecodedArgs(Vec<(String, Token)>);
+
+impl DecodedArgs {
+    pub fn to_json(&self, options: &DecodeOptions) -> Value {
+        Value::Object(
+            self.0
+                .iter()
+                .map(|(name, token)| (name.clone(), token_to_json(token, options)))
+                .collect::<Map<String, Value>>(),
+        )
+    }
+
+    /// JSON text keeping every digit of integers wider than 64 bits, which
+    /// [`to_json`](Self::to_json) can only give as decimal text. `None` unless
+    /// `bigint=number` and there is such an integer.
+    pub fn exact_json(&self, options: &DecodeOptions) -> Option<Box<RawValue>> {
+        if options.bigint != BigIntFormat::Number
+            || !self.0.iter().any(|(_, token)| has_wide_integer(token))
+        {
+            return None;
+        }
+        let args = ExactArgs(&self.0, options);
+        serde_json::value::to_raw_value(&args)
+            .map_err(|e| log::error!("Failed to write decoded arguments: {e}"))
+            .ok()
+    }
+}
+
+/// Addresses and byte strings as `0x` hex, integers following `options.bigint`, arrays
+/// and tuples as arrays.
+fn token_to_json(token: &Token, options: &DecodeOptions) -> Value {
+    match token {
+        Token::Address(address) => Value::String(format!("0x{}", hex::encode(address))),
+        Token::FixedBytes(bytes) | Token::Bytes(bytes) => {
+            Value::String(format!("0x{}", hex::encode(bytes)))
+        }
+        Token::Uint(_) | Token::Int(_) => {
+            let digits = integer_digits(token).unwrap_or_default();
+            match integer_number(&digits) {
+                Some(n) => options.bigint.to_json(n),
+                None => Value::String(digits),
+            }
+        }
+        Token::Bool(value) => Value::Bool(*value),
+        Token::String(value) => Value::String(value.clone()),
+        Token::FixedArray(items) | Token::Array(items) | Token::Tuple(items) => Value::Array(
+            items
+                .iter()
+                .map(|item| token_to_json(item, options))
+                .collect(),
+        ),
+    }
+}
+
+/// Decimal digits of an integer token, signed for `int<N>`.
+fn integer_digits(token: &Token) -> Option<String> {
+    match token {
+        Token::Uint(value) => Some(value.to_string()),
+        Token::Int(value) if value.bit(255) => {
+            // Two's complement: the magnitude is the negation of the raw word
+            let magnitude = (!*value).overflowing_add(U256::one()).0;
+            Some(format!("-{magnitude}"))
+        }
+        Token::Int(value) => Some(value.to_string()),
+        _ => None,
+    }
+}
+
+/// The integer as a JSON number, when an `i64` or `u64` holds it.
+fn integer_number(digits: &str) -> Option<Number> {
+    digits
+        .parse::<i64>()
+        .map(Number::from)
+        .or_else(|_| digits.parse::<u64>().map(Number::from))
+        .ok()
+}
+
+fn has_wide_integer(token: &Token) -> bool {
+    match token {
+        Token::FixedArray(items) | Token::Array(items) | Token::Tuple(items) => {
+            items.iter().any(has_wide_integer)
+        }
+        token => integer_digits(token).is_some_and(|digits| integer_number(&digits).is_none()),
+    }
+}
+
+/// See [`DecodedArgs::exact_json`].
+struct ExactArgs<'a>(&'a [(String, Token)], &'a DecodeOptions);
+
+impl Serialize for ExactArgs<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let ExactArgs(args, options) = *self;
+        serializer.collect_map(
+            args.iter()
+                .map(|(name, token)| (name, ExactToken(token, options))),
+        )
+    }
+}
+
+struct ExactToken<'a>(&'a Token, &'a DecodeOptions);
+
+impl Serialize for ExactToken<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let ExactToken(token, options) = *self;
+        match token {
+            Token::FixedArray(items) | Token::Array(items) | Token::Tuple(items) => {
+                serializer.collect_seq(items.iter().map(|item| ExactToken(item, options)))
+            }
+            token if has_wide_integer(token) => {
+                let digits = integer_digits(token).unwrap_or_default();
+                match exact_number(&digits) {
+                    Some(number) => number.serialize(serializer),
+                    None => serializer.serialize_str(&digits),
+                }
+            }
+            token => token_to_json(token, options).serialize(serializer),
+        }
+    }
+}
+
+fn address(row: &AnyRow, options: &DecodeOptions) -> Option<String> {
+    ADDRESS_COLUMNS
+        .iter()
+        .find_map(|name| column_bytes(row, name, options))
+        .filter(|bytes| bytes.len() == 20)
+        .map(|bytes| format!("0x{}", hex::encode(bytes)))
+}
+
+/// `topics` as an array (or JSON array text) of hex strings, or `topic0`..`topic3`.
+pub fn topics(row: &AnyRow, options: &DecodeOptions) -> Option<Vec<H256>> {
+    let hashes = match column_value(row, "topics", options) {
+        Some(Value::Array(items)) => items,
+        Some(Value::String(text)) => serde_json::from_str(&text).ok()?,
+        _ => (0..4)
+            .map_while(|index| column_value(row, &format!("topic{index}"), options))
+            .collect(),
+    };
+    hashes
+        .iter()
+        .map(|hash| {
+            hash.as_str()
+                .and_then(parse_hex)
+                .filter(|bytes| bytes.len() == 32)
+                .map(|bytes| H256::from_slice(&bytes))
+        })
+        .collect::<Option<Vec<H256>>>()
+        .filter(|topics| !topics.is_empty())
+}
+
+fn column_value(row: &AnyRow, name: &str, options: &DecodeOptions) -> Option<Value> {
+    let col = row.columns().iter().find(|col| col.name() == name)?;
+    try_sql_to_json(row, col, options)
+        .ok()
+        .filter(|value| !value.is_null())
+}
+
+/// A binary column's bytes, or a text column's `0x` hex decoded.
+pub fn column_bytes(row: &AnyRow, name: &str, options: &DecodeOptions) -> Option<Vec<u8>> {
+    let col = row.columns().iter().find(|col| col.name() == name)?;
+    if is_binary(col.type_info()) {
+        let raw = row.try_get_raw(col.ordinal()).ok()?;
+        if raw.is_null() {
+            return None;
+        }
+        return <Vec<u8> as Decode<sqlx::any::Any>>::decode(raw).ok();
+    }
+    match column_value(row, name, options)? {
+        Value::String(text) => parse_hex(&text),
+        _ => None,
+    }
+}
+
+fn parse_hex(text: &str) -> Option<Vec<u8>> {
+    let digits = text.strip_prefix("0x").unwrap_or(text);
+    hex::decode(digits).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sql_to_json::test_database_url;
+    use serde_json::json;
+    use sqlx::Connection;
+
+    const TRANSFER: &str = "0xddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef";
+    const TOKEN: &str = "0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48";
+
+    fn erc20() -> Contract {
+        parse_abi(&json!([
+            {
+                "type": "event",
+                "name": "Transfer",
+                "anonymous": false,
+                "inputs": [
+                    {"name": "from", "type": "address", "indexed": true},
+                    {"name": "to", "type": "address", "indexed": true},
+                    {"name": "value", "type": "uint256", "indexed": false}
+                ]
+            },
+            {
+                "type": "function",
+                "name": "transfer",
+                "stateMutability": "nonpayable",
+                "inputs": [
+                    {"name": "to", "type": "address"},
+                    {"name": "", "type": "int256"}
+                ],
+                "outputs": [{"name": "", "type": "bool"}]
+            }
+        ]))
+        .unwrap()
+    }
+
+    fn word(hex_digits: &str) -> String {
+        format!("{:0>64}", hex_digits)
+    }
+
+    #[test]
+    fn test_decode_event() {
+        let topics = [
+            TRANSFER.to_string(),
+            format!("0x{}", word("1111111111111111111111111111111111111111")),
+            format!("0x{}", word("2222222222222222222222222222222222222222")),
+        ];
+        let topics: Vec<H256> = topics
+            .iter()
+            .map(|t| H256::from_slice(&parse_hex(t).unwrap()))
+            .collect();
+        let data = parse_hex(&word("3e8")).unwrap();
+        let (name, args) = decode_event(&erc20(), &topics, &data).unwrap();
+        assert_eq!(name, "Transfer");
+        assert_eq!(
+            args.to_json(&DecodeOptions::default()),
+            json!({
+                "from": "0x1111111111111111111111111111111111111111",
+                "to": "0x2222222222222222222222222222222222222222",
+                "value": 1000
+            })
+        );
+    }
+
+    #[test]
+    fn test_decode_call() {
+        let selector = erc20().function("transfer").unwrap().short_signature();
+        let input = format!(
+            "0x{}{}{}",
+            hex::encode(selector),
+            word("2222222222222222222222222222222222222222"),
+            "f".repeat(64)
+        );
+        let (name, args) = decode_call(&erc20(), &parse_hex(&input).unwrap()).unwrap();
+        assert_eq!(name, "transfer");
+        assert_eq!(
+            args.to_json(&DecodeOptions::default()),
+            json!({"to": "0x2222222222222222222222222222222222222222", "arg1": -1})
+        );
+        assert!(decode_call(&erc20(), &[0xde, 0xad]).is_none());
+    }
+
+    #[test]
+    fn test_wide_integers_keep_their_digits() {
+        const TEN_POW_21: &str = "1000000000000000000000";
+        const UINT256_MAX: &str =
+            "115792089237316195423570985008687907853269984665640564039457584007913129639935";
+        const INT256_MIN: &str =
+            "-57896044618658097711785492504343953926634992332820282019728792003956564819968";
+        let args = DecodedArgs(vec![
+            ("amount".to_string(), Token::Uint(U256::exp10(21))),
+            ("max".to_string(), Token::Uint(U256::MAX)),
+            ("min".to_string(), Token::Int(U256::one() << 255)),
+            ("small".to_string(), Token::Int(U256::MAX)),
+        ]);
+        let options = |bigint| DecodeOptions {
+            bigint,
+            ..Default::default()
+        };
+
+        for (bigint, small) in [
+            (BigIntFormat::Auto, json!(-1)),
+            (BigIntFormat::String, json!("-1")),
+        ] {
+            assert_eq!(
+                args.to_json(&options(bigint)),
+                json!({"amount": TEN_POW_21, "max": UINT256_MAX, "min": INT256_MIN, "small": small})
+            );
+            assert!(args.exact_json(&options(bigint)).is_none());
+        }
+
+        let number = options(BigIntFormat::Number);
+        assert_eq!(args.to_json(&number)["amount"], TEN_POW_21);
+        assert_eq!(
+            args.exact_json(&number).unwrap().get(),
+            format!(
+                r#"{{"amount":{TEN_POW_21},"max":{UINT256_MAX},"min":{INT256_MIN},"small":-1}}"#
+            )
+        );
+        let narrow = DecodedArgs(vec![("value".to_string(), Token::Uint(U256::from(1000)))]);
+        assert!(narrow.exact_json(&number).is_none());
+    }
+
+    #[test]
+    fn test_normalize_address() {
+        assert_eq!(
+            normalize_address("0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48").unwrap(),
+            TOKEN
+        );
+        assert!(normalize_address("0x1234").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_decode_rows() -> anyhow::Result<()> {
+        let db_url = test_database_url();
+        if !db_url.starts_with("sqlite") {
+            return Ok(());
+        }
+        let mut c = sqlx::AnyConnection::connect(&db_url).await?;
+        let sql = format!(
+            "SELECT '{TOKEN}' AS address, '[\"{TRANSFER}\", \"0x{from}\", \"0x{to}\"]' AS topics, \
+             '0x{value}' AS data \
+             UNION ALL SELECT '0x0000000000000000000000000000000000000001', '[]', '0x'",
+            from = word("11"),
+            to = word("22"),
+            value = word("1"),
+        );
+        let rows = sqlx::query(&sql).fetch_all(&mut c).await?;
+        let contracts = Contracts::from([(("eth".to_string(), TOKEN.to_string()), erc20())]);
+        let taken = vec!["address".to_string(), "args".to_string()];
+        let (columns, appended) = decode_rows(
+            &rows,
+            &["eth"],
+            &contracts,
+            &taken,
+            &DecodeOptions::default(),
+        );
+
+        let names: Vec<&str> = columns.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, vec!["event_name", "function_name", "args_2"]);
+        assert_eq!(appended[0].values, vec![json!("Transfer"), Value::Null]);
+        assert_eq!(appended[1].values, vec![Value::Null, Value::Null]);
+        assert_eq!(appended[2].values[0]["value"], 1);
+        assert_eq!(appended[2].values[1], Value::Null);
+        Ok(())
+    }
+
+    #[rocket::async_test]
+    async fn test_register_requires_admin_key() {
+        use crate::admin::AdminKey;
+        use crate::chains::ChainRegistry;
+        use crate::jobs::JobStore;
+        use crate::schema::SchemaCache;
+        use crate::signatures::SignatureRegistry;
+        use rocket::http::{ContentType, Header, Status};
+        use rocket::local::asynchronous::Client;
+        use sqlx::any::AnyPoolOptions;
+
+        let pool = AnyPoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        let abis = AbiRegistry::new(Duration::from_secs(60), DEFAULT_ABI_SCHEMA.to_string());
+        abis.create_table(&pool).await.unwrap();
+        let rocket = rocket::build()
+            .manage(pool)
+            .manage(ChainRegistry::builtin())
+            .manage(SchemaCache::from_env())
+            .manage(JobStore::from_env())
+            .manage(abis)
+            .manage(AdminKey::new(Some("secret".to_string())))
+            .manage(SignatureRegistry::builtin())
+            .mount("/", crate::api_routes());
+        let client = Client::tracked(rocket).await.unwrap();
+        let body = json!({"chain": "eth", "address": TOKEN, "abi": []}).to_string();
+
+        let response = client
+            .post("/v1/abis")
+            .header(ContentType::JSON)
+            .body(&body)
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Unauthorized);
+        let response = client
+            .post("/v1/abis")
+            .header(ContentType::JSON)
+            .header(Header::new("Authorization", "Bearer wrong"))
+            .body(&body)
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Unauthorized);
+        let response = client
+            .post("/v1/abis")
+            .header(ContentType::JSON)
+            .header(Header::new("Authorization", "Bearer secret"))
+            .body(&body)
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Created);
+
+        let response = client.get(format!("/v1/abis/eth/{TOKEN}")).dispatch().await;
+        assert_eq!(response.status(), Status::Ok);
+        let response = client
+            .get("/v1/run?type_param=indexed&query=SELECT%20abi%20FROM%20sandworm_abis")
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::BadRequest);
+        let error: Value = response.into_json().await.unwrap();
+        assert!(error["error"]
+            .as_str()
+            .unwrap()
+            .starts_with("Only SELECT queries"));
+    }
+}
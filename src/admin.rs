@@ -0,0 +1,86 @@
+use rocket::http::Status;
+use rocket::request::{FromRequest, Outcome};
+use rocket::Request;
+
+/// Key required by administrative endpoints, read from `ADMIN_API_KEY`. Without it those
+/// endpoints are disabled.
+pub struct AdminKey(Option<String>);
+
+impl AdminKey {
+    pub fn new(key: Option<String>) -> Self {
+        AdminKey(key.filter(|key| !key.is_empty()))
+    }
+
+    pub fn from_env() -> Self {
+        Self::new(std::env::var("ADMIN_API_KEY").ok())
+    }
+}
+
+/// Request guard for administrative endpoints: the caller must send
+/// `Authorization: Bearer <ADMIN_API_KEY>`.
+pub struct Admin;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AdminError {
+    /// No admin key is configured.
+    Disabled,
+    /// The key is missing or wrong.
+    Unauthorized,
+}
+
+impl AdminError {
+    pub fn status(self) -> Status {
+        match self {
+            AdminError::Disabled => Status::Forbidden,
+            AdminError::Unauthorized => Status::Unauthorized,
+        }
+    }
+
+    pub fn message(self) -> &'static str {
+        match self {
+            AdminError::Disabled => {
+                "Administrative endpoints are disabled: ADMIN_API_KEY is not set"
+            }
+            AdminError::Unauthorized => "Missing or invalid admin key",
+        }
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for Admin {
+    type Error = AdminError;
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let Some(AdminKey(Some(key))) = request.rocket().state::<AdminKey>() else {
+            return Outcome::Error((Status::Forbidden, AdminError::Disabled));
+        };
+        let given = request
+            .headers()
+            .get_one("Authorization")
+            .and_then(|value| value.strip_prefix("Bearer "));
+        match given {
+            Some(given) if constant_time_eq(given.as_bytes(), key.as_bytes()) => {
+                Outcome::Success(Admin)
+            }
+            _ => Outcome::Error((Status::Unauthorized, AdminError::Unauthorized)),
+        }
+    }
+}
+
+/// Compares without returning early, so response times do not reveal the key.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_constant_time_eq() {
+        assert!(constant_time_eq(b"secret", b"secret"));
+        assert!(!constant_time_eq(b"secret", b"secreT"));
+        assert!(!constant_time_eq(b"secret", b"secret2"));
+        assert!(AdminKey::new(Some(String::new())).0.is_none());
+    }
+}
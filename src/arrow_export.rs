@@ -0,0 +1,713 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use arrow::array::{
+    ArrayRef, BinaryBuilder, BooleanBuilder, Date32Builder, Decimal128Builder, Decimal256Builder,
+    Float64Builder, Int16Builder, Int32Builder, Int64Builder, StringBuilder,
+    Time64MicrosecondBuilder, TimestampMicrosecondBuilder, UInt32Builder, UInt64Builder,
+};
+use arrow::datatypes::{i256, DataType, Field, Schema, TimeUnit};
+use arrow::error::ArrowError;
+use arrow::ipc::writer::StreamWriter;
+use arrow::record_batch::RecordBatch;
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime, NaiveTime, Timelike};
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::errors::ParquetError;
+use parquet::file::properties::WriterProperties;
+use serde_json::Value;
+use sqlx::any::{Any, AnyColumn, AnyRow, AnyValueRef};
+use sqlx::{Column, Decode, Row, TypeInfo, ValueRef};
+
+use crate::encoding::AppendedColumn;
+use crate::response::Column as ResultColumn;
+use crate::sql_to_json::{decode_decimal, logical_type, DecodeWarning, Decoder};
+
+pub const ARROW_STREAM_MEDIA_TYPE: (&str, &str) = ("application", "vnd.apache.arrow.stream");
+
+/// Writer settings for Parquet output.
+#[derive(Debug, Clone)]
+pub struct ParquetOptions {
+    pub compression: Compression,
+    /// Maximum number of rows per row group.
+    pub row_group_size: usize,
+}
+
+impl ParquetOptions {
+    pub const DEFAULT_ROW_GROUP_SIZE: usize = 1024 * 1024;
+}
+
+/// Arrow representation chosen for a backend column, following the type names
+/// `try_sql_nonnull_to_json` dispatches on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ArrowKind {
+    Int16,
+    Int32,
+    Int64,
+    UInt32,
+    UInt64,
+    Float64,
+    Boolean,
+    Decimal,
+    Date,
+    Time,
+    /// Instants, stored as UTC microseconds.
+    TimestampTz,
+    /// Wall-clock date-times without an offset.
+    Timestamp,
+    Binary,
+    Json,
+    Utf8,
+}
+
+/// Chooses the Arrow kind from a column's backend type name and the logical type
+/// [`logical_type`] derived from it, so that described columns without rows map the same
+/// way as decoded ones.
+fn arrow_kind(type_name: &str, logical_type: &str) -> ArrowKind {
+    match type_name {
+        "REAL" | "FLOAT" | "FLOAT4" | "FLOAT8" | "DOUBLE" => ArrowKind::Float64,
+        "NUMERIC" | "DECIMAL" => ArrowKind::Decimal,
+        "INT8" | "BIGINT" | "SERIAL8" | "BIGSERIAL" | "IDENTITY" | "INT64" | "INTEGER8"
+        | "BIGINT SIGNED" => ArrowKind::Int64,
+        "INT" | "INT4" | "INTEGER" | "MEDIUMINT" | "YEAR" => ArrowKind::Int32,
+        "INT2" | "SMALLINT" | "TINYINT" => ArrowKind::Int16,
+        "BIGINT UNSIGNED" => ArrowKind::UInt64,
+        "INT UNSIGNED" | "MEDIUMINT UNSIGNED" | "SMALLINT UNSIGNED" | "TINYINT UNSIGNED" => {
+            ArrowKind::UInt32
+        }
+        "BOOL" | "BOOLEAN" => ArrowKind::Boolean,
+        // MSSQL bits and MySQL BIT(1) are booleans, wider MySQL bit fields are integers
+        "BIT" if logical_type == "bool" => ArrowKind::Boolean,
+        "BIT" if logical_type == "uint64" => ArrowKind::UInt64,
+        "DATE" => ArrowKind::Date,
+        "TIME" | "TIMETZ" => ArrowKind::Time,
+        "DATETIMEOFFSET" | "TIMESTAMP" | "TIMESTAMPTZ" => ArrowKind::TimestampTz,
+        "DATETIME" | "DATETIME2" => ArrowKind::Timestamp,
+        _ if logical_type == "bytes" => ArrowKind::Binary,
+        "JSON" | "JSON[]" | "JSONB" | "JSONB[]" => ArrowKind::Json,
+        _ => ArrowKind::Utf8,
+    }
+}
+
+/// Decodes the value of `col` in the row at `index`, recording a warning with `decoder`
+/// and writing `null` when it fails, as the JSON outputs do.
+fn decode<'r, T: Decode<'r, Any>>(
+    decoder: &mut Decoder,
+    index: usize,
+    col: &AnyColumn,
+    raw: AnyValueRef<'r>,
+) -> Option<T> {
+    T::decode(raw)
+        .map_err(|e| decoder.skip(DecodeWarning::new(index, col, e)))
+        .ok()
+}
+
+fn micros_of_day(time: NaiveTime) -> i64 {
+    i64::from(time.num_seconds_from_midnight()) * 1_000_000 + i64::from(time.nanosecond() / 1_000)
+}
+
+fn days_since_epoch(date: NaiveDate) -> i32 {
+    (date - NaiveDate::from_ymd_opt(1970, 1, 1).unwrap()).num_days() as i32
+}
+
+fn json_field(name: &str) -> Field {
+    Field::new(name, DataType::Utf8, true).with_metadata(HashMap::from([(
+        "ARROW:extension:name".to_string(),
+        "arrow.json".to_string(),
+    )]))
+}
+
+/// Builds one Arrow column from every row's value for `col`. `declared` is the column's
+/// logical type, which may carry a declared `decimal(p,s)`.
+fn build_column(
+    rows: &[AnyRow],
+    col: &AnyColumn,
+    declared: &str,
+    decoder: &mut Decoder,
+) -> Result<(Field, ArrayRef), ArrowError> {
+    let type_info = col.type_info();
+    let kind = arrow_kind(type_info.name(), &logical_type(type_info));
+    let name = col.name();
+    let raw_values = rows.iter().enumerate().map(|(index, row)| {
+        let raw = row
+            .try_get_raw(col.ordinal())
+            .ok()
+            .filter(|raw| !raw.is_null());
+        (index, raw)
+    });
+
+    macro_rules! primitive {
+        ($builder:expr, $ty:ty, $data_type:expr) => {{
+            let mut builder = $builder;
+            for (index, raw) in raw_values {
+                builder.append_option(raw.and_then(|raw| decode::<$ty>(decoder, index, col, raw)));
+            }
+            (
+                Field::new(name, $data_type, true),
+                Arc::new(builder.finish()) as ArrayRef,
+            )
+        }};
+    }
+
+    Ok(match kind {
+        ArrowKind::Int16 => primitive!(Int16Builder::new(), i16, DataType::Int16),
+        ArrowKind::Int32 => primitive!(Int32Builder::new(), i32, DataType::Int32),
+        ArrowKind::Int64 => primitive!(Int64Builder::new(), i64, DataType::Int64),
+        ArrowKind::UInt32 => primitive!(UInt32Builder::new(), u32, DataType::UInt32),
+        ArrowKind::UInt64 => primitive!(UInt64Builder::new(), u64, DataType::UInt64),
+        ArrowKind::Float64 => primitive!(Float64Builder::new(), f64, DataType::Float64),
+        ArrowKind::Boolean => primitive!(BooleanBuilder::new(), bool, DataType::Boolean),
+        ArrowKind::Binary => {
+            let mut builder = BinaryBuilder::new();
+            for (index, raw) in raw_values {
+                builder
+                    .append_option(raw.and_then(|raw| decode::<Vec<u8>>(decoder, index, col, raw)));
+            }
+            (
+                Field::new(name, DataType::Binary, true),
+                Arc::new(builder.finish()) as ArrayRef,
+            )
+        }
+        ArrowKind::Date => {
+            let mut builder = Date32Builder::new();
+            for (index, raw) in raw_values {
+                let value = raw.and_then(|raw| decode::<NaiveDate>(decoder, index, col, raw));
+                builder.append_option(value.map(days_since_epoch));
+            }
+            (
+                Field::new(name, DataType::Date32, true),
+                Arc::new(builder.finish()) as ArrayRef,
+            )
+        }
+        ArrowKind::Time => {
+            let mut builder = Time64MicrosecondBuilder::new();
+            for (index, raw) in raw_values {
+                let value = raw.and_then(|raw| decode::<NaiveTime>(decoder, index, col, raw));
+                builder.append_option(value.map(micros_of_day));
+            }
+            let data_type = DataType::Time64(TimeUnit::Microsecond);
+            (
+                Field::new(name, data_type, true),
+                Arc::new(builder.finish()) as ArrayRef,
+            )
+        }
+        ArrowKind::TimestampTz => {
+            let mut builder = TimestampMicrosecondBuilder::new().with_timezone("UTC");
+            for (index, raw) in raw_values {
+                let value =
+                    raw.and_then(|raw| decode::<DateTime<FixedOffset>>(decoder, index, col, raw));
+                builder.append_option(value.map(|v| v.timestamp_micros()));
+            }
+            let data_type = DataType::Timestamp(TimeUnit::Microsecond, Some("UTC".into()));
+            (
+                Field::new(name, data_type, true),
+                Arc::new(builder.finish()) as ArrayRef,
+            )
+        }
+        ArrowKind::Timestamp => {
+            let mut builder = TimestampMicrosecondBuilder::new();
+            for (index, raw) in raw_values {
+                let value = raw.and_then(|raw| decode::<NaiveDateTime>(decoder, index, col, raw));
+                builder.append_option(value.map(|v| v.and_utc().timestamp_micros()));
+            }
+            let data_type = DataType::Timestamp(TimeUnit::Microsecond, None);
+            (
+                Field::new(name, data_type, true),
+                Arc::new(builder.finish()) as ArrayRef,
+            )
+        }
+        ArrowKind::Decimal => {
+            let texts: Vec<Option<String>> = raw_values
+                .map(|(index, raw)| {
+                    raw.and_then(|raw| {
+                        decode_decimal(raw)
+                            .map_err(|e| decoder.skip(DecodeWarning::new(index, col, e)))
+                            .ok()
+                    })
+                })
+                .collect();
+            decimal_column(name, &texts, declared_decimal(declared))?
+        }
+        ArrowKind::Json | ArrowKind::Utf8 => {
+            let mut builder = StringBuilder::new();
+            for (index, row) in rows.iter().enumerate() {
+                match decoder.value(index, row, col) {
+                    Value::Null => builder.append_null(),
+                    Value::String(s) => builder.append_value(s),
+                    other => builder.append_value(other.to_string()),
+                }
+            }
+            let field = if kind == ArrowKind::Json {
+                json_field(name)
+            } else {
+                Field::new(name, DataType::Utf8, true)
+            };
+            (field, Arc::new(builder.finish()) as ArrayRef)
+        }
+    })
+}
+
+/// Splits exact decimal text into its unscaled digits at `scale`, e.g. ("-1.5", 3) -> "-1500".
+fn unscaled(text: &str, scale: usize) -> String {
+    let (int_part, frac_part) = text.split_once('.').unwrap_or((text, ""));
+    let mut digits = String::with_capacity(int_part.len() + scale);
+    digits.push_str(int_part);
+    digits.push_str(frac_part);
+    digits.extend(std::iter::repeat_n('0', scale - frac_part.len()));
+    digits
+}
+
+/// Precision and scale of a declared `decimal(p,s)` logical type.
+fn declared_decimal(logical_type: &str) -> Option<(usize, usize)> {
+    let (precision, scale) = logical_type
+        .strip_prefix("decimal(")?
+        .strip_suffix(')')?
+        .split_once(',')?;
+    let (precision, scale) = (precision.parse().ok()?, scale.parse().ok()?);
+    (scale <= precision).then_some((precision, scale))
+}
+
+/// Chooses Decimal128 or Decimal256 from the declared precision and scale, or else from
+/// the widest value in the column. Columns that hold NaN/Infinity or exceed 76 digits stay
+/// exact as text.
+fn decimal_column(
+    name: &str,
+    texts: &[Option<String>],
+    declared: Option<(usize, usize)>,
+) -> Result<(Field, ArrayRef), ArrowError> {
+    let mut scale = 0usize;
+    let mut int_digits = 1usize;
+    let mut finite = true;
+    for text in texts.iter().flatten() {
+        if !text
+            .trim_start_matches('-')
+            .starts_with(|c: char| c.is_ascii_digit())
+        {
+            finite = false;
+            continue;
+        }
+        let unsigned = text.trim_start_matches('-');
+        let (int_part, frac_part) = unsigned.split_once('.').unwrap_or((unsigned, ""));
+        scale = scale.max(frac_part.len());
+        int_digits = int_digits.max(int_part.trim_start_matches('0').len().max(1));
+    }
+    let (precision, scale) = declared.unwrap_or((int_digits + scale, scale));
+
+    if finite && precision <= 38 {
+        let mut builder =
+            Decimal128Builder::new().with_precision_and_scale(precision as u8, scale as i8)?;
+        for text in texts {
+            builder.append_option(
+                text.as_ref()
+                    .and_then(|t| unscaled(t, scale).parse::<i128>().ok()),
+            );
+        }
+        let data_type = DataType::Decimal128(precision as u8, scale as i8);
+        return Ok((
+            Field::new(name, data_type, true),
+            Arc::new(builder.finish()),
+        ));
+    }
+    if finite && precision <= 76 {
+        let mut builder =
+            Decimal256Builder::new().with_precision_and_scale(precision as u8, scale as i8)?;
+        for text in texts {
+            builder.append_option(
+                text.as_ref()
+                    .and_then(|t| i256::from_string(&unscaled(t, scale))),
+            );
+        }
+        let data_type = DataType::Decimal256(precision as u8, scale as i8);
+        return Ok((
+            Field::new(name, data_type, true),
+            Arc::new(builder.finish()),
+        ));
+    }
+    let mut builder = StringBuilder::new();
+    for text in texts {
+        builder.append_option(text.as_deref());
+    }
+    Ok((
+        Field::new(name, DataType::Utf8, true),
+        Arc::new(builder.finish()),
+    ))
+}
+
+/// Record batch with one natively typed column per select-list entry, named after `columns`.
+/// Values that fail to decode are written as nulls and reported to `decoder`.
+pub fn batch_from_rows(
+    rows: &[AnyRow],
+    columns: &[ResultColumn],
+    decoder: &mut Decoder,
+) -> Result<RecordBatch, ArrowError> {
+    let Some(first) = rows.first() else {
+        let fields = columns
+            .iter()
+            .map(empty_field)
+            .collect::<Result<Vec<_>, _>>()?;
+        return Ok(RecordBatch::new_empty(Arc::new(Schema::new(fields))));
+    };
+    let (fields, arrays): (Vec<Field>, Vec<ArrayRef>) = first
+        .columns()
+        .iter()
+        .zip(columns)
+        .map(|(col, column)| {
+            build_column(rows, col, &column.logical_type, decoder)
+                .map(|(field, array)| (field.with_name(&column.name), array))
+        })
+        .collect::<Result<Vec<_>, _>>()?
+        .into_iter()
+        .unzip();
+    RecordBatch::try_new(Arc::new(Schema::new(fields)), arrays)
+}
+
+/// Field for a result column without rows, typed from its described backend type.
+/// Decimals without a declared precision and scale have no width to go by and are text.
+fn empty_field(column: &ResultColumn) -> Result<Field, ArrowError> {
+    let name = column.name.as_str();
+    let data_type = match arrow_kind(&column.type_name, &column.logical_type) {
+        ArrowKind::Int16 => DataType::Int16,
+        ArrowKind::Int32 => DataType::Int32,
+        ArrowKind::Int64 => DataType::Int64,
+        ArrowKind::UInt32 => DataType::UInt32,
+        ArrowKind::UInt64 => DataType::UInt64,
+        ArrowKind::Float64 => DataType::Float64,
+        ArrowKind::Boolean => DataType::Boolean,
+        ArrowKind::Binary => DataType::Binary,
+        ArrowKind::Date => DataType::Date32,
+        ArrowKind::Time => DataType::Time64(TimeUnit::Microsecond),
+        ArrowKind::TimestampTz => DataType::Timestamp(TimeUnit::Microsecond, Some("UTC".into())),
+        ArrowKind::Timestamp => DataType::Timestamp(TimeUnit::Microsecond, None),
+        ArrowKind::Decimal => match declared_decimal(&column.logical_type) {
+            Some(declared) => {
+                return decimal_column(name, &[], Some(declared)).map(|(field, _)| field)
+            }
+            None => DataType::Utf8,
+        },
+        ArrowKind::Json => return Ok(json_field(name)),
+        ArrowKind::Utf8 => DataType::Utf8,
+    };
+    Ok(Field::new(name, data_type, true))
+}
+
+/// Record batch from normalized interpreter results (positional rows), typed from the
+/// column types `tabular::json_type` assigned.
+pub fn batch_from_table(
+    columns: &[ResultColumn],
+    rows: &[Value],
+) -> Result<RecordBatch, ArrowError> {
+    let cell = |row: &Value, index: usize| row.get(index).cloned().unwrap_or(Value::Null);
+    let mut fields = Vec::with_capacity(columns.len());
+    let mut arrays: Vec<ArrayRef> = Vec::with_capacity(columns.len());
+    for (index, column) in columns.iter().enumerate() {
+        let name = column.name.as_str();
+        let (field, array): (Field, ArrayRef) = match column.type_name.as_str() {
+            "int64" => {
+                let mut builder = Int64Builder::new();
+                for row in rows {
+                    builder.append_option(cell(row, index).as_i64());
+                }
+                (
+                    Field::new(name, DataType::Int64, true),
+                    Arc::new(builder.finish()),
+                )
+            }
+            "uint64" => {
+                let mut builder = UInt64Builder::new();
+                for row in rows {
+                    builder.append_option(cell(row, index).as_u64());
+                }
+                (
+                    Field::new(name, DataType::UInt64, true),
+                    Arc::new(builder.finish()),
+                )
+            }
+            "float" => {
+                let mut builder = Float64Builder::new();
+                for row in rows {
+                    builder.append_option(cell(row, index).as_f64());
+                }
+                (
+                    Field::new(name, DataType::Float64, true),
+                    Arc::new(builder.finish()),
+                )
+            }
+            "bool" => {
+                let mut builder = BooleanBuilder::new();
+                for row in rows {
+                    builder.append_option(cell(row, index).as_bool());
+                }
+                (
+                    Field::new(name, DataType::Boolean, true),
+                    Arc::new(builder.finish()),
+                )
+            }
+            type_name => {
+                let mut builder = StringBuilder::new();
+                for row in rows {
+                    match cell(row, index) {
+                        Value::Null => builder.append_null(),
+                        Value::String(s) => builder.append_value(s),
+                        other => builder.append_value(other.to_string()),
+                    }
+                }
+                let field = if type_name == "json" {
+                    json_field(name)
+                } else {
+                    Field::new(name, DataType::Utf8, true)
+                };
+                (field, Arc::new(builder.finish()))
+            }
+        };
+        fields.push(field);
+        arrays.push(array);
+    }
+    let schema = Arc::new(Schema::new(fields));
+    if arrays.is_empty() {
+        return Ok(RecordBatch::new_empty(schema));
+    }
+    RecordBatch::try_new(schema, arrays)
+}
+
+/// Adds computed `appended` columns, typed from `columns`, after the columns of `batch`.
+pub fn append_columns(
+    batch: RecordBatch,
+    columns: &[ResultColumn],
+    appended: &[AppendedColumn],
+) -> Result<RecordBatch, ArrowError> {
+    let Some(first) = appended.first() else {
+        return Ok(batch);
+    };
+    let rows: Vec<Value> = (0..first.values.len())
+        .map(|index| Value::Array(appended.iter().map(|c| c.values[index].clone()).collect()))
+        .collect();
+    let extra = batch_from_table(columns, &rows)?;
+    let schema = batch.schema();
+    let fields: Vec<Arc<Field>> = schema
+        .fields()
+        .iter()
+        .chain(extra.schema().fields())
+        .cloned()
+        .collect();
+    let arrays = batch
+        .columns()
+        .iter()
+        .chain(extra.columns())
+        .cloned()
+        .collect();
+    RecordBatch::try_new(
+        Arc::new(Schema::new(fields).with_metadata(schema.metadata().clone())),
+        arrays,
+    )
+}
+
+/// Replaces the schema metadata of `batch`.
+pub fn with_metadata(
+    batch: RecordBatch,
+    metadata: HashMap<String, String>,
+) -> Result<RecordBatch, ArrowError> {
+    let schema = batch.schema().as_ref().clone().with_metadata(metadata);
+    batch.with_schema(Arc::new(schema))
+}
+
+/// Serializes a batch as an Arrow IPC stream.
+pub fn to_ipc_stream(batch: &RecordBatch) -> Result<Vec<u8>, ArrowError> {
+    let mut writer = StreamWriter::try_new(Vec::new(), &batch.schema())?;
+    writer.write(batch)?;
+    writer.finish()?;
+    writer.into_inner()
+}
+
+/// Serializes a batch as a Parquet file. Schema metadata is kept as key/value metadata.
+pub fn to_parquet(batch: &RecordBatch, options: &ParquetOptions) -> Result<Vec<u8>, ParquetError> {
+    let properties = WriterProperties::builder()
+        .set_compression(options.compression)
+        .set_max_row_group_size(options.row_group_size)
+        .build();
+    let mut writer = ArrowWriter::try_new(Vec::new(), batch.schema(), Some(properties))?;
+    writer.write(batch)?;
+    writer.into_inner()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sql_to_json::DecodeOptions;
+    use arrow::array::{Array, Decimal128Array, Decimal256Array, Int64Array};
+    use serde_json::json;
+
+    #[test]
+    fn test_decimal_column_picks_width() {
+        let texts = vec![Some("123.45".to_string()), None, Some("-0.5".to_string())];
+        let (field, array) = decimal_column("amount", &texts, None).unwrap();
+        assert_eq!(field.data_type(), &DataType::Decimal128(5, 2));
+        let array = array.as_any().downcast_ref::<Decimal128Array>().unwrap();
+        assert_eq!(array.value(0), 12345);
+        assert!(array.is_null(1));
+        assert_eq!(array.value(2), -50);
+
+        let wide = "1".repeat(60) + ".0001";
+        let (field, array) = decimal_column("supply", &[Some(wide)], None).unwrap();
+        assert_eq!(field.data_type(), &DataType::Decimal256(64, 4));
+        let array = array.as_any().downcast_ref::<Decimal256Array>().unwrap();
+        assert_eq!(array.value(0).to_string(), "1".repeat(60) + "0001");
+
+        // 2^256 - 1 needs 78 digits, more than Decimal256 holds
+        let max_u256 =
+            "115792089237316195423570985008687907853269984665640564039457584007913129639935";
+        let (field, _) = decimal_column("balance", &[Some(max_u256.to_string())], None).unwrap();
+        assert_eq!(field.data_type(), &DataType::Utf8);
+
+        let (field, _) = decimal_column("ratio", &[Some("NaN".to_string())], None).unwrap();
+        assert_eq!(field.data_type(), &DataType::Utf8);
+
+        let declared = declared_decimal("decimal(20,9)");
+        let (field, array) = decimal_column("fee", &[Some("1.5".to_string())], declared).unwrap();
+        assert_eq!(field.data_type(), &DataType::Decimal128(20, 9));
+        let array = array.as_any().downcast_ref::<Decimal128Array>().unwrap();
+        assert_eq!(array.value(0), 1_500_000_000);
+    }
+
+    #[test]
+    fn test_batch_from_table_roundtrip() {
+        let columns = vec![
+            ResultColumn {
+                name: "n".to_string(),
+                type_name: "int64".to_string(),
+                logical_type: "int64".to_string(),
+                nullable: None,
+                unsupported: false,
+            },
+            ResultColumn {
+                name: "data".to_string(),
+                type_name: "json".to_string(),
+                logical_type: "json".to_string(),
+                nullable: None,
+                unsupported: false,
+            },
+        ];
+        let rows = vec![json!([1, {"a": 1}]), json!([null, null])];
+        let batch = batch_from_table(&columns, &rows).unwrap();
+        assert_eq!(batch.num_rows(), 2);
+        let n = batch
+            .column(0)
+            .as_any()
+            .downcast_ref::<Int64Array>()
+            .unwrap();
+        assert_eq!(n.value(0), 1);
+        assert!(n.is_null(1));
+        assert_eq!(
+            batch
+                .schema()
+                .field(1)
+                .metadata()
+                .get("ARROW:extension:name")
+                .map(String::as_str),
+            Some("arrow.json")
+        );
+        assert!(!to_ipc_stream(&batch).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_empty_batch_keeps_column_types() {
+        use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+        let column = |name: &str, type_name: &str, logical_type: &str| ResultColumn {
+            name: name.to_string(),
+            type_name: type_name.to_string(),
+            logical_type: logical_type.to_string(),
+            nullable: None,
+            unsupported: false,
+        };
+        let columns = vec![
+            column("number", "INT8", "int64"),
+            column("hash", "BYTEA", "bytes"),
+            column("flag", "BIT", "bool"),
+            column("time", "TIMESTAMPTZ", "timestamptz"),
+            column("data", "JSONB", "json"),
+            column("label", "TEXT", "text"),
+            column("fee", "NUMERIC", "decimal(20,9)"),
+            column("supply", "NUMERIC", "decimal(78,0)"),
+            column("amount", "NUMERIC", "decimal"),
+        ];
+        let mut decoder = Decoder::new(DecodeOptions::default());
+        let batch = batch_from_rows(&[], &columns, &mut decoder).unwrap();
+        assert_eq!(batch.num_rows(), 0);
+        let schema = batch.schema();
+        let types: Vec<&DataType> = schema.fields().iter().map(|f| f.data_type()).collect();
+        assert_eq!(
+            types,
+            vec![
+                &DataType::Int64,
+                &DataType::Binary,
+                &DataType::Boolean,
+                &DataType::Timestamp(TimeUnit::Microsecond, Some("UTC".into())),
+                &DataType::Utf8,
+                &DataType::Utf8,
+                &DataType::Decimal128(20, 9),
+                &DataType::Utf8,
+                &DataType::Utf8,
+            ]
+        );
+        assert_eq!(schema.field(0).name(), "number");
+        assert!(schema
+            .field(4)
+            .metadata()
+            .contains_key("ARROW:extension:name"));
+
+        let options = ParquetOptions {
+            compression: Compression::UNCOMPRESSED,
+            row_group_size: ParquetOptions::DEFAULT_ROW_GROUP_SIZE,
+        };
+        let parquet = to_parquet(&batch, &options).unwrap();
+        let reader = ParquetRecordBatchReaderBuilder::try_new(bytes::Bytes::from(parquet)).unwrap();
+        assert_eq!(reader.schema().fields().len(), columns.len());
+        assert_eq!(reader.schema().field(1).data_type(), &DataType::Binary);
+    }
+
+    #[test]
+    fn test_append_columns() {
+        let column = |name: &str, type_name: &str| ResultColumn {
+            name: name.to_string(),
+            type_name: type_name.to_string(),
+            logical_type: type_name.to_string(),
+            nullable: None,
+            unsupported: false,
+        };
+        let rows = vec![json!([1]), json!([2])];
+        let batch = batch_from_table(&[column("n", "int64")], &rows).unwrap();
+        let appended = vec![AppendedColumn {
+            name: "event_name".to_string(),
+            values: vec![json!("Transfer"), Value::Null],
+            json: Vec::new(),
+        }];
+        let batch = append_columns(batch, &[column("event_name", "text")], &appended).unwrap();
+        assert_eq!(batch.num_columns(), 2);
+        assert_eq!(batch.schema().field(1).name(), "event_name");
+        assert_eq!(batch.schema().field(1).data_type(), &DataType::Utf8);
+        assert!(batch.column(1).is_null(1));
+    }
+
+    #[test]
+    fn test_parquet_row_groups() {
+        use parquet::file::reader::{FileReader, SerializedFileReader};
+
+        let columns = vec![ResultColumn {
+            name: "n".to_string(),
+            type_name: "int64".to_string(),
+            logical_type: "int64".to_string(),
+            nullable: None,
+            unsupported: false,
+        }];
+        let rows: Vec<Value> = (0..10).map(|n| json!([n])).collect();
+        let batch = batch_from_table(&columns, &rows).unwrap();
+        let options = ParquetOptions {
+            compression: Compression::ZSTD(Default::default()),
+            row_group_size: 4,
+        };
+        let file = to_parquet(&batch, &options).unwrap();
+        let reader = SerializedFileReader::new(bytes::Bytes::from(file)).unwrap();
+        assert_eq!(reader.metadata().file_metadata().num_rows(), 10);
+        assert_eq!(reader.num_row_groups(), 3);
+    }
+}
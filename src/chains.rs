@@ -0,0 +1,277 @@
+use std::collections::HashMap;
+use std::sync::LazyLock;
+
+use anyhow::{bail, Context};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+const BUILTIN_CHAINS: &str = include_str!("../config/chains.json");
+
+/// `chain.table` qualifiers, e.g. `eth.logs`; the first group is the chain.
+pub static CHAIN_TABLE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\b([a-zA-Z0-9_]+)\.([a-zA-Z0-9_]+)\b").unwrap());
+
+/// EQL/SuiQL chain clauses, e.g. `GET balance FROM account 0x0 ON eth, arbitrum`. Only the
+/// `ON` of a `GET` statement counts, which [`ChainRegistry::referenced_chains`] further
+/// requires to end the statement.
+static ON_CHAINS: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)(?:^|;)\s*GET\b[^;]*?\bON\s+([a-z0-9_]+(?:\s*,\s*[a-z0-9_]+)*)").unwrap()
+});
+
+static STRING_LITERAL: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"'(?:[^']|'')*'").unwrap());
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ChainFamily {
+    Evm,
+    Sui,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Availability {
+    Rpc,
+    Indexed,
+    Both,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Chain {
+    pub id: String,
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    pub family: ChainFamily,
+    pub chain_id: Option<u64>,
+    pub native_currency: String,
+    pub decimals: u8,
+    pub availability: Availability,
+}
+
+impl Chain {
+    pub fn rpc(&self) -> bool {
+        matches!(self.availability, Availability::Rpc | Availability::Both)
+    }
+
+    pub fn indexed(&self) -> bool {
+        matches!(self.availability, Availability::Indexed | Availability::Both)
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ChainsResponse {
+    pub chains: Vec<Chain>,
+}
+
+#[derive(Deserialize)]
+struct ChainsConfig {
+    chains: Vec<Chain>,
+}
+
+/// Every chain the server knows about, looked up by id or alias (case-insensitive).
+#[derive(Debug)]
+pub struct ChainRegistry {
+    chains: Vec<Chain>,
+    by_name: HashMap<String, usize>,
+}
+
+impl ChainRegistry {
+    pub fn from_json(json: &str) -> anyhow::Result<Self> {
+        let config: ChainsConfig = serde_json::from_str(json).context("Invalid chain registry")?;
+        let mut by_name = HashMap::new();
+        for (index, chain) in config.chains.iter().enumerate() {
+            for name in std::iter::once(&chain.id).chain(&chain.aliases) {
+                if by_name.insert(name.to_lowercase(), index).is_some() {
+                    bail!("Chain name '{}' is registered more than once", name);
+                }
+            }
+        }
+        Ok(ChainRegistry {
+            chains: config.chains,
+            by_name,
+        })
+    }
+
+    /// The registry bundled with the server.
+    pub fn builtin() -> Self {
+        Self::from_json(BUILTIN_CHAINS).expect("Bundled chain registry must be valid")
+    }
+
+    /// Loads the registry from `CHAINS_CONFIG` if set, otherwise falls back to the bundled one.
+    pub fn from_env() -> anyhow::Result<Self> {
+        match std::env::var("CHAINS_CONFIG") {
+            Ok(path) => {
+                let json = std::fs::read_to_string(&path)
+                    .with_context(|| format!("Could not read chain registry from {}", path))?;
+                Self::from_json(&json)
+            }
+            Err(_) => Ok(Self::builtin()),
+        }
+    }
+
+    pub fn chains(&self) -> &[Chain] {
+        &self.chains
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Chain> {
+        self.by_name
+            .get(&name.to_lowercase())
+            .map(|&index| &self.chains[index])
+    }
+
+    /// Splits a physical table name such as `eth_blocks` into its chain and table. Chain ids
+    /// may themselves contain `_`, so the longest id that prefixes the name wins.
+    pub fn split_physical<'a>(&self, physical_name: &'a str) -> Option<(&Chain, &'a str)> {
+        self.chains
+            .iter()
+            .filter_map(|chain| {
+                let table = physical_name
+                    .strip_prefix(chain.id.as_str())?
+                    .strip_prefix('_')?;
+                Some((chain, table)).filter(|_| !table.is_empty())
+            })
+            .max_by_key(|(chain, _)| chain.id.len())
+    }
+
+    /// Chains a query refers to through `chain.table` qualifiers or `ON <chain>` clauses, in
+    /// order of first appearance. Other identifiers, such as a column named `base`, and
+    /// string literals are not references.
+    pub fn referenced_chains(&self, query: &str) -> Vec<&Chain> {
+        let query = STRING_LITERAL.replace_all(query, "''");
+        let qualifiers = CHAIN_TABLE
+            .captures_iter(&query)
+            .filter_map(|caps| caps.get(1))
+            .map(|name| (name.start(), name.as_str()));
+        let clauses = ON_CHAINS.captures_iter(&query).filter_map(|caps| {
+            let list = caps.get(1)?;
+            // The chain list is followed only by the end of the statement or a `>> file` export
+            let rest = query[list.end()..].trim_start();
+            (rest.is_empty() || rest.starts_with(';') || rest.starts_with(">>")).then_some(list)
+        });
+        let clauses = clauses.flat_map(|list| {
+            list.as_str()
+                .split(',')
+                .map(move |name| (list.start(), name.trim()))
+        });
+        let mut names: Vec<(usize, &str)> = qualifiers.chain(clauses).collect();
+        names.sort_by_key(|(start, _)| *start);
+
+        let mut chains: Vec<&Chain> = Vec::new();
+        for (_, name) in names {
+            if let Some(chain) = self.get(name) {
+                if !chains.iter().any(|c| c.id == chain.id) {
+                    chains.push(chain);
+                }
+            }
+        }
+        chains
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_registry_lookup() {
+        let registry = ChainRegistry::builtin();
+        assert_eq!(registry.get("eth").unwrap().chain_id, Some(1));
+        assert_eq!(registry.get("Ethereum").unwrap().id, "eth");
+        assert_eq!(registry.get("suitest").unwrap().family, ChainFamily::Sui);
+        assert!(registry.get("unknown").is_none());
+    }
+
+    #[test]
+    fn test_duplicate_alias_is_rejected() {
+        let json = r#"{"chains": [
+            {"id": "eth", "family": "evm", "chain_id": 1, "native_currency": "ETH", "decimals": 18, "availability": "both"},
+            {"id": "eth2", "aliases": ["ETH"], "family": "evm", "chain_id": 2, "native_currency": "ETH", "decimals": 18, "availability": "rpc"}
+        ]}"#;
+        assert!(ChainRegistry::from_json(json).is_err());
+    }
+
+    #[test]
+    fn test_split_physical_prefers_longest_id() {
+        let json = r#"{"chains": [
+            {"id": "eth", "family": "evm", "chain_id": 1, "native_currency": "ETH", "decimals": 18, "availability": "both"},
+            {"id": "eth_holesky", "family": "evm", "chain_id": 17000, "native_currency": "ETH", "decimals": 18, "availability": "both"}
+        ]}"#;
+        let registry = ChainRegistry::from_json(json).unwrap();
+        let split = |name| {
+            registry
+                .split_physical(name)
+                .map(|(c, t)| (c.id.as_str(), t))
+        };
+        assert_eq!(split("eth_holesky_blocks"), Some(("eth_holesky", "blocks")));
+        assert_eq!(split("eth_blocks"), Some(("eth", "blocks")));
+        assert_eq!(split("eth_holesky_"), Some(("eth", "holesky_")));
+        assert_eq!(split("eth_"), None);
+        assert_eq!(split("ethereum_blocks"), None);
+    }
+
+    #[test]
+    fn test_referenced_chains() {
+        let registry = ChainRegistry::builtin();
+        let ids: Vec<&str> = registry
+            .referenced_chains("SELECT * FROM eth.blocks JOIN arbitrum.blocks ON eth.blocks.number = 1")
+            .iter()
+            .map(|c| c.id.as_str())
+            .collect();
+        assert_eq!(ids, vec!["eth", "arb"]);
+    }
+
+    #[test]
+    fn test_columns_and_literals_are_not_chains() {
+        let registry = ChainRegistry::builtin();
+        let ids = |query| -> Vec<String> {
+            registry
+                .referenced_chains(query)
+                .iter()
+                .map(|c| c.id.clone())
+                .collect()
+        };
+        assert_eq!(ids("SELECT base, op FROM eth.logs"), vec!["eth"]);
+        assert_eq!(
+            ids("SELECT * FROM eth.logs l JOIN eth.blocks b ON l.celo = b.mina WHERE l.x = 'base.logs'"),
+            vec!["eth"]
+        );
+        assert_eq!(
+            ids("SELECT sui FROM t JOIN u ON op = 1"),
+            Vec::<String>::new()
+        );
+        assert_eq!(ids("GET sui FROM account 0x0 ON eth"), vec!["eth"]);
+        assert_eq!(
+            ids("GET balance FROM account 0x0 ON eth, arbitrum"),
+            vec!["eth", "arb"]
+        );
+        assert_eq!(
+            ids("GET balance FROM account 0x0 ON base >> balances.csv; GET nonce FROM account 0x0 ON op"),
+            vec!["base", "op"]
+        );
+    }
+
+    #[test]
+    fn test_join_conditions_are_not_chain_clauses() {
+        let registry = ChainRegistry::builtin();
+        let ids = |query| -> Vec<String> {
+            registry
+                .referenced_chains(query)
+                .iter()
+                .map(|c| c.id.clone())
+                .collect()
+        };
+        assert_eq!(
+            ids("SELECT * FROM eth.logs l JOIN eth.blocks b ON base IS NOT NULL"),
+            vec!["eth"]
+        );
+        assert_eq!(
+            ids("SELECT * FROM eth.logs l JOIN t ON base IN (1, 2)"),
+            vec!["eth"]
+        );
+        assert_eq!(ids("SELECT * FROM t JOIN u ON sui"), Vec::<String>::new());
+        assert_eq!(
+            ids("SELECT * FROM t JOIN u ON op, base"),
+            Vec::<String>::new()
+        );
+    }
+}
@@ -0,0 +1,236 @@
+use async_compression::tokio::bufread::{BrotliEncoder, GzipEncoder, ZstdEncoder};
+use async_compression::Level;
+use rocket::fairing::{Fairing, Info, Kind};
+use rocket::http::{Header, Status};
+use rocket::{Request, Response};
+use tokio::io::{AsyncRead, AsyncReadExt, BufReader};
+
+const DEFAULT_MIN_SIZE: usize = 1024;
+
+/// Content types that are already compressed and would only grow.
+const PRECOMPRESSED: &[(&str, &str)] = &[
+    ("application", "vnd.apache.parquet"),
+    ("application", "gzip"),
+    ("application", "zip"),
+    ("application", "zstd"),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Algorithm {
+    Brotli,
+    Zstd,
+    Gzip,
+}
+
+impl Algorithm {
+    /// Preferred first when the client weights several encodings equally.
+    const ALL: [Algorithm; 3] = [Algorithm::Brotli, Algorithm::Zstd, Algorithm::Gzip];
+
+    fn token(self) -> &'static str {
+        match self {
+            Algorithm::Brotli => "br",
+            Algorithm::Zstd => "zstd",
+            Algorithm::Gzip => "gzip",
+        }
+    }
+
+    /// Picks the encoding with the highest `q` value in an `Accept-Encoding` header.
+    pub fn negotiate(accept_encoding: &str) -> Option<Self> {
+        let weights: Vec<(&str, f32)> = accept_encoding
+            .split(',')
+            .filter_map(|item| {
+                let mut parts = item.split(';');
+                let token = parts.next()?.trim();
+                let q = parts
+                    .find_map(|param| param.trim().strip_prefix("q="))
+                    .map_or(Some(1.0), |q| q.trim().parse::<f32>().ok())?;
+                (!token.is_empty()).then_some((token, q))
+            })
+            .collect();
+        let weight = |algorithm: Algorithm| {
+            let named = weights
+                .iter()
+                .find(|(token, _)| token.eq_ignore_ascii_case(algorithm.token()));
+            named
+                .or_else(|| weights.iter().find(|(token, _)| *token == "*"))
+                .map_or(0.0, |(_, q)| *q)
+        };
+        Self::ALL
+            .into_iter()
+            .map(|algorithm| (algorithm, weight(algorithm)))
+            .filter(|(_, q)| *q > 0.0)
+            .fold(
+                None,
+                |best: Option<(Algorithm, f32)>, (algorithm, q)| match best {
+                    Some((_, best_q)) if best_q >= q => best,
+                    _ => Some((algorithm, q)),
+                },
+            )
+            .map(|(algorithm, _)| algorithm)
+    }
+
+    fn encode<'r, R: AsyncRead + Send + Unpin + 'r>(
+        self,
+        body: R,
+        level: Level,
+    ) -> Box<dyn AsyncRead + Send + Unpin + 'r> {
+        let body = BufReader::new(body);
+        match self {
+            Algorithm::Brotli => Box::new(BrotliEncoder::with_quality(body, level)),
+            Algorithm::Zstd => Box::new(ZstdEncoder::with_quality(body, level)),
+            Algorithm::Gzip => Box::new(GzipEncoder::with_quality(body, level)),
+        }
+    }
+}
+
+/// Compresses response bodies according to the request's `Accept-Encoding`.
+///
+/// Bodies of known size below `min_size` are sent as is; streamed bodies are always
+/// compressed as they are read.
+pub struct Compression {
+    pub min_size: usize,
+    pub level: Level,
+}
+
+impl Compression {
+    /// Reads `COMPRESSION_MIN_SIZE` (bytes, default 1024) and `COMPRESSION_LEVEL`
+    /// (algorithm-specific quality, library default when unset).
+    pub fn from_env() -> Self {
+        let min_size = std::env::var("COMPRESSION_MIN_SIZE")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_MIN_SIZE);
+        let level = std::env::var("COMPRESSION_LEVEL")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .map_or(Level::Default, Level::Precise);
+        Compression { min_size, level }
+    }
+}
+
+#[rocket::async_trait]
+impl Fairing for Compression {
+    fn info(&self) -> Info {
+        Info {
+            name: "Compressing response bodies",
+            kind: Kind::Response,
+        }
+    }
+
+    async fn on_response<'r>(&self, request: &'r Request<'_>, response: &mut Response<'r>) {
+        let status = response.status();
+        if status == Status::NoContent
+            || status == Status::NotModified
+            || response.headers().contains("Content-Encoding")
+        {
+            return;
+        }
+        if let Some(content_type) = response.content_type() {
+            let media_type = (content_type.top().as_str(), content_type.sub().as_str());
+            if PRECOMPRESSED.contains(&media_type) {
+                return;
+            }
+        }
+        response.adjoin_header(Header::new("Vary", "Accept-Encoding"));
+
+        let Some(algorithm) = request
+            .headers()
+            .get("Accept-Encoding")
+            .find_map(Algorithm::negotiate)
+        else {
+            return;
+        };
+
+        let size = response.body_mut().preset_size();
+        if size.is_some_and(|size| size < self.min_size) {
+            return;
+        }
+        let body = response.body_mut().take();
+        match size {
+            // Buffered bodies stay sized so clients still get a Content-Length
+            Some(size) => {
+                let mut compressed = Vec::with_capacity(size / 4);
+                let mut encoder = algorithm.encode(body, self.level);
+                if let Err(e) = encoder.read_to_end(&mut compressed).await {
+                    log::error!("Failed to compress response body: {e}");
+                    response.set_status(Status::InternalServerError);
+                    return;
+                }
+                response.set_sized_body(compressed.len(), std::io::Cursor::new(compressed));
+            }
+            None => response.set_streamed_body(algorithm.encode(body, self.level)),
+        }
+        response.set_header(Header::new("Content-Encoding", algorithm.token()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_compression::tokio::bufread::GzipDecoder;
+    use rocket::http::ContentType;
+    use rocket::local::asynchronous::Client;
+
+    #[test]
+    fn test_negotiate() {
+        assert_eq!(Algorithm::negotiate("gzip, deflate"), Some(Algorithm::Gzip));
+        assert_eq!(
+            Algorithm::negotiate("gzip, br, zstd"),
+            Some(Algorithm::Brotli)
+        );
+        assert_eq!(
+            Algorithm::negotiate("br;q=0.5, zstd;q=0.8"),
+            Some(Algorithm::Zstd)
+        );
+        assert_eq!(
+            Algorithm::negotiate("*;q=0.1, br;q=0"),
+            Some(Algorithm::Zstd)
+        );
+        assert_eq!(Algorithm::negotiate("identity"), None);
+        assert_eq!(Algorithm::negotiate(""), None);
+    }
+
+    #[rocket::get("/big")]
+    fn big() -> (ContentType, String) {
+        (ContentType::JSON, "[1,2,3],".repeat(1000))
+    }
+
+    #[rocket::get("/small")]
+    fn small() -> (ContentType, &'static str) {
+        (ContentType::JSON, "[]")
+    }
+
+    #[rocket::async_test]
+    async fn test_compresses_large_bodies() {
+        let rocket = rocket::build()
+            .attach(Compression {
+                min_size: 1024,
+                level: Level::Default,
+            })
+            .mount("/", rocket::routes![big, small]);
+        let client = Client::tracked(rocket).await.unwrap();
+
+        let response = client
+            .get("/big")
+            .header(Header::new("Accept-Encoding", "gzip"))
+            .dispatch()
+            .await;
+        assert_eq!(response.headers().get_one("Content-Encoding"), Some("gzip"));
+        assert_eq!(response.headers().get_one("Vary"), Some("Accept-Encoding"));
+        let compressed = response.into_bytes().await.unwrap();
+        let mut body = String::new();
+        GzipDecoder::new(compressed.as_slice())
+            .read_to_string(&mut body)
+            .await
+            .unwrap();
+        assert_eq!(body, "[1,2,3],".repeat(1000));
+
+        let response = client
+            .get("/small")
+            .header(Header::new("Accept-Encoding", "gzip"))
+            .dispatch()
+            .await;
+        assert_eq!(response.headers().get_one("Content-Encoding"), None);
+        assert_eq!(response.into_string().await.as_deref(), Some("[]"));
+    }
+}
@@ -0,0 +1,513 @@
+use std::cell::RefCell;
+
+use serde::ser::{SerializeMap, SerializeSeq};
+use serde::{Serialize, Serializer};
+use serde_json::value::RawValue;
+use serde_json::Value;
+use sqlx::any::{AnyColumn, AnyRow};
+use sqlx::{Column, Decode, Row, ValueRef};
+
+use crate::sql_to_json::{
+    exact_number, is_binary, logical_type, suffixed_names, DecimalFormat, DecodeOptions,
+    DecodeWarning, Decoder, DuplicatePolicy,
+};
+
+/// How indexed rows are laid out in the envelope's `rows`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Layout {
+    /// One object per row in select-list order, duplicate column names handled like `row_to_json`.
+    Objects,
+    /// One positional array per row.
+    Arrays,
+    /// One array per column.
+    ColumnArrays,
+}
+
+/// Serializes indexed rows straight from the driver rows into the output, without
+/// building a JSON value per row first. Cells go through the same type dispatch as
+/// [`try_sql_to_json`](crate::sql_to_json::try_sql_to_json); values that fail to decode
+/// are written as `null` and collected for [`IndexedRows::decode_warnings`], or fail the
+/// serialization when `strict` is set.
+pub struct IndexedRows<'a> {
+    pub rows: &'a [AnyRow],
+    pub layout: Layout,
+    pub options: DecodeOptions,
+    /// Write binary columns as byte strings, for MessagePack and CBOR. Otherwise cells
+    /// are written as their JSON values.
+    pub native: bool,
+    pub strict: bool,
+    /// Computed columns written after each row's own columns.
+    pub appended: &'a [AppendedColumn],
+    decoder: RefCell<Decoder>,
+}
+
+/// Values computed for each row after the query ran, such as `decode=abi` columns.
+#[derive(Debug, Clone)]
+pub struct AppendedColumn {
+    pub name: String,
+    /// One value per row.
+    pub values: Vec<Value>,
+    /// Per row, JSON text for values holding numbers a [`Value`] cannot, such as 256-bit
+    /// integers. JSON output writes it in place of the value; empty when no row needs it.
+    pub json: Vec<Option<Box<RawValue>>>,
+}
+
+impl<'a> IndexedRows<'a> {
+    pub fn new(rows: &'a [AnyRow], layout: Layout, options: DecodeOptions) -> Self {
+        IndexedRows {
+            rows,
+            layout,
+            options,
+            native: false,
+            strict: false,
+            appended: &[],
+            decoder: RefCell::new(Decoder::new(options)),
+        }
+    }
+
+    pub fn native(mut self, native: bool) -> Self {
+        self.native = native;
+        self
+    }
+
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    pub fn appended(mut self, appended: &'a [AppendedColumn]) -> Self {
+        self.appended = appended;
+        self
+    }
+
+    fn appended_cell(&self, column: &'a AppendedColumn, index: usize) -> AppendedCell<'a> {
+        AppendedCell {
+            value: column.values.get(index).unwrap_or(&NULL),
+            json: if self.native {
+                None
+            } else {
+                column.json.get(index).and_then(Option::as_deref)
+            },
+        }
+    }
+
+    /// The values that failed to decode so far. Serialized after the rows, this lists
+    /// every failure in them.
+    pub fn decode_warnings(&self) -> DecodeWarnings<'_> {
+        DecodeWarnings(&self.decoder)
+    }
+}
+
+/// See [`IndexedRows::decode_warnings`].
+pub struct DecodeWarnings<'a>(&'a RefCell<Decoder>);
+
+impl Serialize for DecodeWarnings<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.0.borrow().warnings().serialize(serializer)
+    }
+}
+
+struct Cell<'a> {
+    index: usize,
+    row: &'a AnyRow,
+    col: &'a AnyColumn,
+    rows: &'a IndexedRows<'a>,
+}
+
+/// A cell's value, decoded before it is written.
+enum Decoded {
+    /// Binary values written as byte strings, see [`IndexedRows::native`].
+    Bytes(Vec<u8>),
+    Value(Value),
+}
+
+impl Cell<'_> {
+    /// Reports a value that failed to decode, which is written as `null` unless strict.
+    fn skip<E: serde::ser::Error>(&self, warning: DecodeWarning) -> Result<Value, E> {
+        if self.rows.strict {
+            return Err(E::custom(warning));
+        }
+        Ok(self.rows.decoder.borrow_mut().skip(warning))
+    }
+
+    fn decode<E: serde::ser::Error>(&self) -> Result<Decoded, E> {
+        let (index, row, col) = (self.index, self.row, self.col);
+        // Dispatch on the value's type like the JSON path does: SQLite columns computed
+        // from expressions have no declared type
+        if self.rows.native {
+            if let Ok(raw) = row.try_get_raw(col.ordinal()) {
+                if !raw.is_null() && is_binary(&raw.type_info()) {
+                    return match <Vec<u8> as Decode<sqlx::any::Any>>::decode(raw) {
+                        Ok(bytes) => Ok(Decoded::Bytes(bytes)),
+                        Err(e) => self
+                            .skip(DecodeWarning::new(index, col, e))
+                            .map(Decoded::Value),
+                    };
+                }
+            }
+        }
+        let decoded = self.rows.decoder.borrow().try_value(index, row, col);
+        match decoded {
+            Ok(value) => Ok(Decoded::Value(value)),
+            Err(warning) => self.skip(warning).map(Decoded::Value),
+        }
+    }
+
+    fn write<S: Serializer>(&self, decoded: &Decoded, serializer: S) -> Result<S::Ok, S::Error> {
+        let value = match decoded {
+            Decoded::Bytes(bytes) => return serializer.serialize_bytes(bytes),
+            Decoded::Value(value) => value,
+        };
+        // Decimals too precise for a JSON value keep their digits when writing JSON text
+        if let Value::String(text) = value {
+            if !self.rows.native
+                && self.rows.options.decimal == DecimalFormat::Number
+                && logical_type(self.col.type_info()).starts_with("decimal")
+            {
+                if let Some(number) = exact_number(text) {
+                    return number.serialize(serializer);
+                }
+            }
+        }
+        value.serialize(serializer)
+    }
+}
+
+impl Serialize for Cell<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let decoded = self.decode()?;
+        self.write(&decoded, serializer)
+    }
+}
+
+/// A decoded value written the way its cell writes it.
+struct Written<'a>(&'a Cell<'a>, &'a Decoded);
+
+impl Serialize for Written<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.0.write(self.1, serializer)
+    }
+}
+
+/// Cells sharing a name under `duplicates=merge`, written as one array. Array values are
+/// spliced in rather than nested, as [`add_value_to_map`] does for decoded rows.
+///
+/// [`add_value_to_map`]: crate::sql_to_json::add_value_to_map
+struct MergedCells<'a>(Vec<Cell<'a>>);
+
+impl Serialize for MergedCells<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let decoded = self
+            .0
+            .iter()
+            .map(|cell| cell.decode())
+            .collect::<Result<Vec<Decoded>, S::Error>>()?;
+        let len = decoded
+            .iter()
+            .map(|decoded| match decoded {
+                Decoded::Value(Value::Array(items)) => items.len(),
+                _ => 1,
+            })
+            .sum();
+        let mut seq = serializer.serialize_seq(Some(len))?;
+        for (cell, decoded) in self.0.iter().zip(&decoded) {
+            match decoded {
+                Decoded::Value(Value::Array(items)) => {
+                    for item in items {
+                        seq.serialize_element(item)?;
+                    }
+                }
+                decoded => seq.serialize_element(&Written(cell, decoded))?,
+            }
+        }
+        seq.end()
+    }
+}
+
+static NULL: Value = Value::Null;
+
+/// A computed value, written from its exact JSON text when there is one.
+struct AppendedCell<'a> {
+    value: &'a Value,
+    json: Option<&'a RawValue>,
+}
+
+impl Serialize for AppendedCell<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.json {
+            Some(json) => json.serialize(serializer),
+            None => self.value.serialize(serializer),
+        }
+    }
+}
+
+struct RowObject<'a>(usize, &'a AnyRow, &'a IndexedRows<'a>);
+
+impl Serialize for RowObject<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let RowObject(index, row, rows) = *self;
+        let columns = row.columns();
+        let cell = |col| Cell {
+            index,
+            row,
+            col,
+            rows,
+        };
+        if rows.options.duplicates != DuplicatePolicy::Merge {
+            let keys = suffixed_names(columns.iter().map(|col| col.name()));
+            let mut map = serializer.serialize_map(Some(keys.len() + rows.appended.len()))?;
+            for (col, key) in columns.iter().zip(keys) {
+                map.serialize_entry(&key, &cell(col))?;
+            }
+            for column in rows.appended {
+                map.serialize_entry(&column.name, &rows.appended_cell(column, index))?;
+            }
+            return map.end();
+        }
+        let mut names: Vec<&str> = Vec::with_capacity(columns.len());
+        for col in columns {
+            if !names.contains(&col.name()) {
+                names.push(col.name());
+            }
+        }
+        let mut map = serializer.serialize_map(Some(names.len() + rows.appended.len()))?;
+        for name in names {
+            let mut same_name = columns.iter().filter(|col| col.name() == name);
+            let first = same_name.next().expect("name comes from the columns");
+            if same_name.next().is_none() {
+                map.serialize_entry(name, &cell(first))?;
+            } else {
+                let cells = columns
+                    .iter()
+                    .filter(|col| col.name() == name)
+                    .map(cell)
+                    .collect();
+                map.serialize_entry(name, &MergedCells(cells))?;
+            }
+        }
+        for column in rows.appended {
+            map.serialize_entry(&column.name, &rows.appended_cell(column, index))?;
+        }
+        map.end()
+    }
+}
+
+struct RowArray<'a>(usize, &'a AnyRow, &'a IndexedRows<'a>);
+
+impl Serialize for RowArray<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let RowArray(index, row, rows) = *self;
+        let columns = row.columns();
+        let mut seq = serializer.serialize_seq(Some(columns.len() + rows.appended.len()))?;
+        for col in columns {
+            seq.serialize_element(&Cell {
+                index,
+                row,
+                col,
+                rows,
+            })?;
+        }
+        for column in rows.appended {
+            seq.serialize_element(&rows.appended_cell(column, index))?;
+        }
+        seq.end()
+    }
+}
+
+struct ColumnArray<'a> {
+    col: &'a AnyColumn,
+    rows: &'a IndexedRows<'a>,
+}
+
+impl Serialize for ColumnArray<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let (col, rows) = (self.col, self.rows);
+        serializer.collect_seq(rows.rows.iter().enumerate().map(|(index, row)| Cell {
+            index,
+            row,
+            col,
+            rows,
+        }))
+    }
+}
+
+impl Serialize for IndexedRows<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let rows = self.rows.iter().enumerate();
+        match self.layout {
+            Layout::Objects => {
+                serializer.collect_seq(rows.map(|(index, row)| RowObject(index, row, self)))
+            }
+            Layout::Arrays => {
+                serializer.collect_seq(rows.map(|(index, row)| RowArray(index, row, self)))
+            }
+            Layout::ColumnArrays => {
+                let columns = self
+                    .rows
+                    .first()
+                    .map(|row| row.columns())
+                    .unwrap_or_default();
+                let mut seq =
+                    serializer.serialize_seq(Some(columns.len() + self.appended.len()))?;
+                for col in columns {
+                    seq.serialize_element(&ColumnArray { col, rows: self })?;
+                }
+                for column in self.appended {
+                    let cells: Vec<AppendedCell> = (0..column.values.len())
+                        .map(|index| self.appended_cell(column, index))
+                        .collect();
+                    seq.serialize_element(&cells)?;
+                }
+                seq.end()
+            }
+        }
+    }
+}
+
+/// The body of the legacy `/run` endpoint:
+/// `{"type": "Wql", "data": [{"result": {"indexed": rows}}]}`.
+#[derive(Serialize)]
+pub struct WqlBody<'a> {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    data: [WqlResult<'a>; 1],
+}
+
+#[derive(Serialize)]
+struct WqlResult<'a> {
+    result: WqlIndexed<'a>,
+}
+
+#[derive(Serialize)]
+struct WqlIndexed<'a> {
+    indexed: IndexedRows<'a>,
+}
+
+impl<'a> WqlBody<'a> {
+    pub fn new(indexed: IndexedRows<'a>) -> Self {
+        WqlBody {
+            kind: "Wql",
+            data: [WqlResult {
+                result: WqlIndexed { indexed },
+            }],
+        }
+    }
+}
+
+pub fn to_msgpack<T: Serialize>(value: &T) -> Result<Vec<u8>, rmp_serde::encode::Error> {
+    rmp_serde::to_vec_named(value)
+}
+
+pub fn to_cbor<T: Serialize>(value: &T) -> Result<Vec<u8>, ciborium::ser::Error<std::io::Error>> {
+    let mut body = Vec::new();
+    ciborium::into_writer(value, &mut body)?;
+    Ok(body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sql_to_json::test_database_url;
+    use ciborium::Value as Cbor;
+    use sqlx::Connection;
+
+    #[test]
+    fn test_appended_json_keeps_exact_numbers() {
+        let exact = RawValue::from_string(r#"{"value":1000000000000000000000}"#.to_string());
+        let appended = [AppendedColumn {
+            name: "args".to_string(),
+            values: vec![serde_json::json!({"value": "1000000000000000000000"})],
+            json: vec![Some(exact.unwrap())],
+        }];
+        let rows = IndexedRows::new(&[], Layout::ColumnArrays, DecodeOptions::default())
+            .appended(&appended);
+        assert_eq!(
+            serde_json::to_string(&rows).unwrap(),
+            r#"[[{"value":1000000000000000000000}]]"#
+        );
+
+        let mut cbor = Vec::new();
+        ciborium::into_writer(&rows.native(true), &mut cbor).unwrap();
+        let cbor: Cbor = ciborium::from_reader(cbor.as_slice()).unwrap();
+        let value = &cbor.as_array().unwrap()[0].as_array().unwrap()[0];
+        let (_, amount) = &value.as_map().unwrap()[0];
+        assert_eq!(amount.as_text(), Some("1000000000000000000000"));
+    }
+
+    #[tokio::test]
+    async fn test_binary_columns_are_byte_strings() -> anyhow::Result<()> {
+        let db_url = test_database_url();
+        if !db_url.starts_with("sqlite") {
+            return Ok(());
+        }
+        let mut c = sqlx::AnyConnection::connect(&db_url).await?;
+        let rows =
+            sqlx::query("SELECT CAST('hello' AS BLOB) AS data, 'text' AS label, 2.5 AS ratio")
+                .fetch_all(&mut c)
+                .await?;
+
+        let objects =
+            IndexedRows::new(&rows, Layout::Objects, DecodeOptions::default()).native(true);
+        let decoded: Cbor = ciborium::from_reader(to_cbor(&objects)?.as_slice())?;
+        assert_eq!(
+            decoded,
+            Cbor::Array(vec![Cbor::Map(vec![
+                (Cbor::Text("data".into()), Cbor::Bytes(b"hello".to_vec())),
+                (Cbor::Text("label".into()), Cbor::Text("text".into())),
+                (Cbor::Text("ratio".into()), Cbor::Float(2.5)),
+            ])])
+        );
+
+        let arrays = IndexedRows::new(&rows, Layout::Arrays, DecodeOptions::default()).native(true);
+        let decoded: Cbor = rmp_serde::from_slice(&to_msgpack(&arrays)?)?;
+        assert_eq!(
+            decoded,
+            Cbor::Array(vec![Cbor::Array(vec![
+                Cbor::Bytes(b"hello".to_vec()),
+                Cbor::Text("text".into()),
+                Cbor::Float(2.5),
+            ])])
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_json_rows_match_decoder() -> anyhow::Result<()> {
+        let db_url = test_database_url();
+        if !db_url.starts_with("sqlite") {
+            return Ok(());
+        }
+        let mut c = sqlx::AnyConnection::connect(&db_url).await?;
+        sqlx::query("CREATE TEMPORARY TABLE events (label TEXT, n INTEGER)")
+            .execute(&mut c)
+            .await?;
+        // The second label is not UTF-8, so it fails to decode
+        sqlx::query("INSERT INTO events VALUES ('ok', 1), (CAST(X'FF' AS TEXT), 2)")
+            .execute(&mut c)
+            .await?;
+        let rows = sqlx::query("SELECT label, n, n FROM events ORDER BY n")
+            .fetch_all(&mut c)
+            .await?;
+
+        let mut decoder = Decoder::new(DecodeOptions::default());
+        for layout in [Layout::Objects, Layout::Arrays, Layout::ColumnArrays] {
+            let expected = match layout {
+                Layout::Objects => decoder.objects(&rows),
+                Layout::Arrays => decoder.arrays(&rows),
+                Layout::ColumnArrays => decoder.column_arrays(&rows),
+            };
+            let streamed = IndexedRows::new(&rows, layout, DecodeOptions::default());
+            let written: Value = serde_json::from_slice(&serde_json::to_vec(&streamed)?)?;
+            assert_eq!(written, Value::Array(expected));
+            let warnings = serde_json::to_value(streamed.decode_warnings())?;
+            assert_eq!(warnings[0]["row"], 1);
+            assert_eq!(warnings[0]["column"], "label");
+        }
+
+        let strict =
+            IndexedRows::new(&rows, Layout::Objects, DecodeOptions::default()).strict(true);
+        let error = serde_json::to_vec(&strict).unwrap_err();
+        assert!(error.to_string().contains("row 1, column 'label'"));
+        Ok(())
+    }
+}
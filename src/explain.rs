@@ -0,0 +1,173 @@
+use std::collections::BTreeSet;
+
+use serde::Serialize;
+use serde_json::Value;
+use sqlx::any::AnyPool;
+use sqlx::Row;
+use utoipa::ToSchema;
+
+use crate::pipeline::ResolvedTable;
+use crate::sql_to_json::sql_to_json;
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct PlanSummary {
+    pub total_cost: Option<f64>,
+    pub startup_cost: Option<f64>,
+    pub estimated_rows: Option<f64>,
+    /// Distinct node types that read tables, e.g. `Seq Scan`, `Index Only Scan`.
+    pub scan_types: Vec<String>,
+    pub indexes: Vec<String>,
+    /// User-facing names of the tables that are read sequentially.
+    pub sequential_scans: Vec<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct Explanation {
+    /// Postgres `EXPLAIN (FORMAT JSON)` output with `chain.table` relation names.
+    #[schema(value_type = Object)]
+    pub plan: Value,
+    pub summary: PlanSummary,
+}
+
+/// Runs `EXPLAIN (FORMAT JSON)` on an already validated and flattened query.
+/// ANALYZE is never used, so the query itself is not executed.
+pub async fn explain(
+    pool: &AnyPool,
+    sql: &str,
+    tables: &[ResolvedTable],
+) -> Result<Explanation, sqlx::Error> {
+    let row = sqlx::query(&format!("EXPLAIN (FORMAT JSON) {}", sql))
+        .fetch_one(pool)
+        .await?;
+    let mut plan = match row.columns().first() {
+        Some(col) => sql_to_json(&row, col),
+        None => Value::Null,
+    };
+    // Some drivers report the plan column as text rather than json
+    if let Value::String(raw) = &plan {
+        plan = serde_json::from_str(raw).map_err(|e| sqlx::Error::Decode(Box::new(e)))?;
+    }
+
+    // The output is a one-element array wrapping {"Plan": {...}}
+    if let Value::Array(items) = &mut plan {
+        if items.len() == 1 {
+            plan = items.remove(0);
+        }
+    }
+    rename_relations(&mut plan, tables);
+    let summary = summarize(&plan);
+    Ok(Explanation { plan, summary })
+}
+
+fn user_facing_name(physical: &str, tables: &[ResolvedTable]) -> Option<String> {
+    tables
+        .iter()
+        .find(|t| t.physical == physical)
+        .map(|t| t.table.clone())
+}
+
+/// Replaces physical table names (`eth_blocks`) with `chain.table` names throughout the plan.
+fn rename_relations(node: &mut Value, tables: &[ResolvedTable]) {
+    match node {
+        Value::Object(map) => {
+            for key in ["Relation Name", "Alias"] {
+                if let Some(Value::String(name)) = map.get_mut(key) {
+                    if let Some(table) = user_facing_name(name, tables) {
+                        *name = table;
+                    }
+                }
+            }
+            for value in map.values_mut() {
+                rename_relations(value, tables);
+            }
+        }
+        Value::Array(items) => {
+            for item in items {
+                rename_relations(item, tables);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn summarize(plan: &Value) -> PlanSummary {
+    let root = plan.get("Plan");
+    let number = |key: &str| root.and_then(|r| r.get(key)).and_then(Value::as_f64);
+
+    let mut scan_types = BTreeSet::new();
+    let mut indexes = BTreeSet::new();
+    let mut sequential_scans = BTreeSet::new();
+    if let Some(root) = root {
+        collect_scans(root, &mut scan_types, &mut indexes, &mut sequential_scans);
+    }
+
+    PlanSummary {
+        total_cost: number("Total Cost"),
+        startup_cost: number("Startup Cost"),
+        estimated_rows: number("Plan Rows"),
+        scan_types: scan_types.into_iter().collect(),
+        indexes: indexes.into_iter().collect(),
+        sequential_scans: sequential_scans.into_iter().collect(),
+    }
+}
+
+fn collect_scans(
+    node: &Value,
+    scan_types: &mut BTreeSet<String>,
+    indexes: &mut BTreeSet<String>,
+    sequential_scans: &mut BTreeSet<String>,
+) {
+    let node_type = node.get("Node Type").and_then(Value::as_str).unwrap_or("");
+    if node_type.ends_with("Scan") {
+        scan_types.insert(node_type.to_string());
+    }
+    if let Some(index) = node.get("Index Name").and_then(Value::as_str) {
+        indexes.insert(index.to_string());
+    }
+    if node_type == "Seq Scan" {
+        if let Some(relation) = node.get("Relation Name").and_then(Value::as_str) {
+            sequential_scans.insert(relation.to_string());
+        }
+    }
+    if let Some(Value::Array(children)) = node.get("Plans") {
+        for child in children {
+            collect_scans(child, scan_types, indexes, sequential_scans);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_summarize_maps_relations_back() {
+        let tables = vec![ResolvedTable {
+            reference: "ethereum.logs".to_string(),
+            table: "eth.logs".to_string(),
+            physical: "eth_logs".to_string(),
+        }];
+        let mut plan = json!({
+            "Plan": {
+                "Node Type": "Nested Loop",
+                "Startup Cost": 0.5,
+                "Total Cost": 120.25,
+                "Plan Rows": 10,
+                "Plans": [
+                    {"Node Type": "Seq Scan", "Relation Name": "eth_logs", "Alias": "eth_logs"},
+                    {"Node Type": "Index Scan", "Relation Name": "blocks", "Index Name": "blocks_pkey"}
+                ]
+            }
+        });
+        rename_relations(&mut plan, &tables);
+        let summary = summarize(&plan);
+
+        assert_eq!(plan["Plan"]["Plans"][0]["Relation Name"], "eth.logs");
+        assert_eq!(plan["Plan"]["Plans"][0]["Alias"], "eth.logs");
+        assert_eq!(summary.total_cost, Some(120.25));
+        assert_eq!(summary.scan_types, vec!["Index Scan", "Seq Scan"]);
+        assert_eq!(summary.indexes, vec!["blocks_pkey"]);
+        assert_eq!(summary.sequential_scans, vec!["eth.logs"]);
+    }
+}
@@ -0,0 +1,95 @@
+use serde_json::Value;
+
+use crate::response::Column;
+
+/// Settings for delimiter-separated output.
+#[derive(Debug, Clone)]
+pub struct DelimitedOptions {
+    pub delimiter: u8,
+    /// Written for SQL NULL / JSON null cells.
+    pub null: String,
+}
+
+/// Writes a header row from `columns` followed by `rows` (positional arrays) using
+/// RFC 4180 quoting. Nested JSON values are written as JSON text.
+pub fn to_delimited(
+    columns: &[Column],
+    rows: &[Value],
+    options: &DelimitedOptions,
+) -> Result<Vec<u8>, csv::Error> {
+    let mut writer = csv::WriterBuilder::new()
+        .delimiter(options.delimiter)
+        .terminator(csv::Terminator::CRLF)
+        .quote_style(csv::QuoteStyle::Necessary)
+        .from_writer(Vec::new());
+
+    writer.write_record(columns.iter().map(|c| c.name.as_str()))?;
+    for row in rows {
+        let Value::Array(cells) = row else {
+            continue;
+        };
+        writer.write_record(cells.iter().map(|cell| cell_to_text(cell, &options.null)))?;
+    }
+    writer
+        .into_inner()
+        .map_err(|e| csv::Error::from(e.into_error()))
+}
+
+fn cell_to_text(cell: &Value, null: &str) -> String {
+    match cell {
+        Value::Null => null.to_string(),
+        Value::String(s) => s.clone(),
+        Value::Bool(b) => b.to_string(),
+        Value::Number(n) => n.to_string(),
+        nested => nested.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn columns(names: &[&str]) -> Vec<Column> {
+        names
+            .iter()
+            .map(|name| Column {
+                name: name.to_string(),
+                type_name: "text".to_string(),
+                logical_type: "text".to_string(),
+                nullable: None,
+                unsupported: false,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_csv_quoting_and_nested_values() {
+        let rows = vec![
+            json!(["0xabc", 1, "a,\"quoted\" value", {"k": [1, 2]}]),
+            json!([null, 2.5, "line\nbreak", true]),
+        ];
+        let options = DelimitedOptions {
+            delimiter: b',',
+            null: "NULL".to_string(),
+        };
+        let csv = to_delimited(&columns(&["hash", "n", "text", "data"]), &rows, &options).unwrap();
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "hash,n,text,data\r\n\
+             0xabc,1,\"a,\"\"quoted\"\" value\",\"{\"\"k\"\":[1,2]}\"\r\n\
+             NULL,2.5,\"line\nbreak\",true\r\n"
+        );
+    }
+
+    #[test]
+    fn test_tsv() {
+        let rows = vec![json!(["x y", null])];
+        let options = DelimitedOptions {
+            delimiter: b'\t',
+            null: String::new(),
+        };
+        let tsv = to_delimited(&columns(&["a", "b"]), &rows, &options).unwrap();
+        assert_eq!(String::from_utf8(tsv).unwrap(), "a\tb\r\nx y\t\r\n");
+    }
+}
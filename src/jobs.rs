@@ -0,0 +1,321 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use chrono::Utc;
+use rocket::http::Status;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::ExecutedQuery;
+
+const DEFAULT_JOB_TTL_SECS: u64 = 3600;
+const DEFAULT_MAX_RUNNING_JOBS: usize = 8;
+const DEFAULT_MAX_STORED_JOBS: usize = 100;
+
+#[derive(Deserialize, ToSchema)]
+pub struct JobRequest {
+    pub query: String,
+    #[serde(rename = "type")]
+    pub type_param: String,
+    /// Maximum number of rows kept for the result, capped at `MAX_RESULT_ROWS`.
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum JobStatus {
+    Running,
+    Succeeded,
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct Job {
+    pub id: String,
+    pub status: JobStatus,
+    /// RFC 3339 timestamps.
+    pub submitted_at: String,
+    pub finished_at: Option<String>,
+    /// Rows kept for the result, once the job has succeeded.
+    pub row_count: Option<usize>,
+    /// Why the job failed.
+    pub error: Option<String>,
+}
+
+/// Why a job was not accepted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JobRejection {
+    /// `max_running` jobs are already running.
+    TooManyRunning,
+    /// `max_stored` jobs are running or holding results.
+    StoreFull,
+}
+
+impl JobRejection {
+    pub fn status(self) -> Status {
+        match self {
+            JobRejection::TooManyRunning => Status::TooManyRequests,
+            JobRejection::StoreFull => Status::ServiceUnavailable,
+        }
+    }
+
+    pub fn message(self) -> &'static str {
+        match self {
+            JobRejection::TooManyRunning => "Too many jobs are running, try again later",
+            JobRejection::StoreFull => "Too many job results are stored, try again later",
+        }
+    }
+}
+
+struct Entry {
+    job: Job,
+    result: Option<Arc<ExecutedQuery>>,
+    finished: Option<Instant>,
+}
+
+/// Queries run in the background, kept in memory until `ttl` after they finish so that
+/// their results can be downloaded in any format. At most `max_running` jobs run at
+/// once and at most `max_stored` are kept, running or not. Clones share the same jobs,
+/// so a running job can record its outcome.
+#[derive(Clone)]
+pub struct JobStore {
+    ttl: Duration,
+    max_running: usize,
+    max_stored: usize,
+    jobs: Arc<Mutex<HashMap<String, Entry>>>,
+}
+
+impl JobStore {
+    pub fn new(ttl: Duration, max_running: usize, max_stored: usize) -> Self {
+        JobStore {
+            ttl,
+            max_running,
+            max_stored,
+            jobs: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Reads `JOB_TTL_SECS` (default 3600), `JOB_MAX_RUNNING` (default 8) and
+    /// `JOB_MAX_STORED` (default 100).
+    pub fn from_env() -> Self {
+        fn var<T: std::str::FromStr>(name: &str, default: T) -> T {
+            std::env::var(name)
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(default)
+        }
+        Self::new(
+            Duration::from_secs(var("JOB_TTL_SECS", DEFAULT_JOB_TTL_SECS)),
+            var("JOB_MAX_RUNNING", DEFAULT_MAX_RUNNING_JOBS),
+            var("JOB_MAX_STORED", DEFAULT_MAX_STORED_JOBS),
+        )
+    }
+
+    /// Drops jobs that finished more than `ttl` ago.
+    fn evict_expired(&self, jobs: &mut HashMap<String, Entry>) {
+        jobs.retain(|_, entry| entry.finished.is_none_or(|f| f.elapsed() < self.ttl));
+    }
+
+    /// Registers a running job, unless the running or stored job limit is reached.
+    pub fn submit(&self) -> Result<Job, JobRejection> {
+        let mut jobs = self.jobs.lock().unwrap();
+        self.evict_expired(&mut jobs);
+        let running = jobs
+            .values()
+            .filter(|entry| entry.finished.is_none())
+            .count();
+        if running >= self.max_running {
+            return Err(JobRejection::TooManyRunning);
+        }
+        if jobs.len() >= self.max_stored {
+            return Err(JobRejection::StoreFull);
+        }
+        let job = Job {
+            id: uuid::Uuid::new_v4().to_string(),
+            status: JobStatus::Running,
+            submitted_at: Utc::now().to_rfc3339(),
+            finished_at: None,
+            row_count: None,
+            error: None,
+        };
+        jobs.insert(
+            job.id.clone(),
+            Entry {
+                job: job.clone(),
+                result: None,
+                finished: None,
+            },
+        );
+        Ok(job)
+    }
+
+    /// Records the outcome of a running job.
+    pub fn finish(&self, id: &str, result: Result<ExecutedQuery, String>) {
+        let mut jobs = self.jobs.lock().unwrap();
+        let Some(entry) = jobs.get_mut(id) else {
+            return;
+        };
+        entry.job.finished_at = Some(Utc::now().to_rfc3339());
+        entry.finished = Some(Instant::now());
+        match result {
+            Ok(executed) => {
+                entry.job.status = JobStatus::Succeeded;
+                entry.job.row_count = Some(executed.row_count());
+                entry.result = Some(Arc::new(executed));
+            }
+            Err(error) => {
+                entry.job.status = JobStatus::Failed;
+                entry.job.error = Some(error);
+            }
+        }
+    }
+
+    pub fn get(&self, id: &str) -> Option<Job> {
+        let mut jobs = self.jobs.lock().unwrap();
+        self.evict_expired(&mut jobs);
+        jobs.get(id).map(|entry| entry.job.clone())
+    }
+
+    /// The job and, once it has succeeded, its result.
+    pub fn result(&self, id: &str) -> Option<(Job, Option<Arc<ExecutedQuery>>)> {
+        let mut jobs = self.jobs.lock().unwrap();
+        self.evict_expired(&mut jobs);
+        jobs.get(id)
+            .map(|entry| (entry.job.clone(), entry.result.clone()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::abi::AbiRegistry;
+    use crate::chains::ChainRegistry;
+    use crate::schema::SchemaCache;
+    use crate::signatures::SignatureRegistry;
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+    use rocket::http::ContentType;
+    use rocket::local::asynchronous::Client;
+    use serde_json::Value;
+    use sqlx::any::AnyPoolOptions;
+
+    #[test]
+    fn test_failed_job_keeps_error() {
+        let store = JobStore::new(Duration::from_secs(60), 1, 1);
+        let job = store.submit().unwrap();
+        assert_eq!(store.get(&job.id).unwrap().status, JobStatus::Running);
+        store.finish(&job.id, Err("no such table".to_string()));
+        let (job, result) = store.result(&job.id).unwrap();
+        assert_eq!(job.status, JobStatus::Failed);
+        assert_eq!(job.error.as_deref(), Some("no such table"));
+        assert!(job.finished_at.is_some());
+        assert!(result.is_none());
+    }
+
+    #[test]
+    fn test_finished_jobs_expire() {
+        let store = JobStore::new(Duration::ZERO, 2, 2);
+        let finished = store.submit().unwrap();
+        store.finish(&finished.id, Err("failed".to_string()));
+        let running = store.submit().unwrap();
+        assert!(store.get(&finished.id).is_none());
+        assert!(store.get(&running.id).is_some());
+    }
+
+    #[test]
+    fn test_expired_jobs_are_evicted_on_reads() {
+        let store = JobStore::new(Duration::ZERO, 1, 1);
+        let job = store.submit().unwrap();
+        store.finish(&job.id, Err("failed".to_string()));
+        assert!(store.result(&job.id).is_none());
+        assert!(store.jobs.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_job_limits() {
+        let store = JobStore::new(Duration::from_secs(60), 1, 2);
+        let first = store.submit().unwrap();
+        assert_eq!(store.submit().unwrap_err(), JobRejection::TooManyRunning);
+        store.finish(&first.id, Err("failed".to_string()));
+        let second = store.submit().unwrap();
+        store.finish(&second.id, Err("failed".to_string()));
+        assert_eq!(store.submit().unwrap_err(), JobRejection::StoreFull);
+        assert_eq!(
+            JobRejection::TooManyRunning.status(),
+            Status::TooManyRequests
+        );
+        assert_eq!(JobRejection::StoreFull.status(), Status::ServiceUnavailable);
+    }
+
+    #[rocket::async_test]
+    async fn test_parquet_job_result() {
+        let pool = AnyPoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        sqlx::query("CREATE TABLE eth_blocks (number INTEGER, hash TEXT)")
+            .execute(&pool)
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO eth_blocks VALUES (1, '0x01'), (2, '0x02'), (3, '0x03')")
+            .execute(&pool)
+            .await
+            .unwrap();
+        let rocket = rocket::build()
+            .manage(pool)
+            .manage(ChainRegistry::builtin())
+            .manage(SchemaCache::from_env())
+            .manage(JobStore::new(Duration::from_secs(60), 1, 1))
+            .manage(AbiRegistry::from_env())
+            .manage(SignatureRegistry::builtin())
+            .mount("/", crate::api_routes());
+        let client = Client::tracked(rocket).await.unwrap();
+
+        let response = client
+            .post("/v1/jobs")
+            .header(ContentType::JSON)
+            .body(r#"{"type": "indexed", "query": "SELECT number, hash FROM eth.blocks", "limit": 2}"#)
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Accepted);
+        let job: Value = response.into_json().await.unwrap();
+        let id = job["id"].as_str().unwrap();
+
+        let mut status = job["status"].clone();
+        for _ in 0..100 {
+            if status != "running" {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            let response = client.get(format!("/v1/jobs/{id}")).dispatch().await;
+            status = response.into_json::<Value>().await.unwrap()["status"].clone();
+        }
+        assert_eq!(status, "succeeded");
+
+        let response = client
+            .get(format!("/v1/jobs/{id}/result?format=parquet"))
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+        let body = response.into_bytes().await.unwrap();
+        let reader = ParquetRecordBatchReaderBuilder::try_new(bytes::Bytes::from(body)).unwrap();
+        assert_eq!(reader.metadata().file_metadata().num_rows(), 2);
+        let schema = reader.schema();
+        assert_eq!(schema.fields().len(), 2);
+        assert_eq!(
+            schema.metadata().get("truncated").map(String::as_str),
+            Some("true")
+        );
+
+        let response = client
+            .get(format!("/v1/jobs/{id}/result?limit=1"))
+            .dispatch()
+            .await;
+        let envelope: Value = response.into_json().await.unwrap();
+        assert_eq!(envelope["row_count"], 2);
+
+        let response = client.get("/v1/jobs/unknown/result").dispatch().await;
+        assert_eq!(response.status(), Status::NotFound);
+    }
+}
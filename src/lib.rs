@@ -0,0 +1,5 @@
+//! Row decoding and serialization, shared by the API server and the benchmarks.
+
+pub mod encoding;
+pub mod pg_binary;
+pub mod sql_to_json;
@@ -1,24 +1,40 @@
 use rocket::{
     fairing::{Fairing, Info, Kind},
-    http::{Header, Status},
+    http::{Accept, Header, Status},
     response::{content::RawJson, status, Response},
+    serde::json::Json,
     Request, State,
 };
 
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use eql_core::{
     common::query_result::QueryResult as EqlQueryResult, interpreter::Interpreter as EQlInterpreter,
 };
 use serde_json::{json, Value};
-use sql_to_json::row_to_json;
 use sui_ql_core::{
     common::query_result::QueryResult as SuiQueryResult,
     interpreter::Interpreter as SuiQlInterpreter,
 };
 
+use std::time::Instant;
+
 use dotenv::dotenv;
-use sqlx::any::AnyPool;
-use crate::utils::json_error;
+use futures::{StreamExt, TryStreamExt};
+use sqlx::any::{AnyKind, AnyPool};
+use sqlx::Executor;
+use utoipa::ToSchema;
+use crate::abi::{AbiRegistration, AbiRegistry};
+use crate::admin::{Admin, AdminError, AdminKey};
+use crate::chains::{ChainFamily, ChainRegistry, ChainsResponse};
+use crate::compression::Compression;
+use crate::encoding::{IndexedRows, Layout, WqlBody};
+use crate::jobs::{JobRequest, JobStatus, JobStore};
+use crate::pipeline::{Engine, Stage, Validation};
+use crate::response::{Encoded, Envelope, QueryOptions, QueryOutput, RequestId};
+use crate::schema::{SchemaCache, TablesResponse};
+use crate::signatures::{SignatureRegistry, SignaturesResponse};
+use crate::sql_to_json::{DecimalFormat, DecodeOptions};
+use crate::utils::{json_error, json_response};
 
 
 pub struct CORS;
@@ -44,7 +60,22 @@ impl Fairing for CORS {
 }
 
 mod utils;
-mod sql_to_json;
+mod abi;
+mod admin;
+mod arrow_export;
+mod chains;
+mod compression;
+mod explain;
+mod export;
+mod jobs;
+mod openapi;
+mod pipeline;
+mod response;
+mod schema;
+mod signatures;
+mod tabular;
+
+use sandworm_api::{encoding, sql_to_json};
 
 #[macro_use]
 extern crate rocket;
@@ -56,46 +87,57 @@ pub enum QueryResult {
     Eql(Vec<EqlQueryResult>),
 }
 
+#[utoipa::path(
+    get,
+    path = "/",
+    responses((status = 200, description = "Server is up", body = String))
+)]
 #[get("/")]
 fn index() -> &'static str {
     "Sandworm API Server is up and running!"
 }
 
+#[utoipa::path(
+    get,
+    path = "/health",
+    responses((status = 200, description = "Server is healthy", body = Value, example = json!({"status": "healthy"})))
+)]
 #[get("/health")]
 fn health() -> RawJson<String> {
     RawJson("{\"status\":\"healthy\"}".to_string())
 }
 
+#[utoipa::path(
+    get,
+    path = "/run",
+    params(
+        ("type_param" = String, Query, description = "Either `rpc` or `indexed`"),
+        ("query" = String, Query, description = "SuiQL, EQL or SQL query to run"),
+    ),
+    responses(
+        (status = 200, description = "Query results", body = Value),
+        (status = 400, description = "Query was rejected before execution", body = ErrorResponse),
+        (status = 500, description = "Query failed", body = ErrorResponse),
+    )
+)]
 #[get("/run?<type_param>&<query>")]
 async fn run_query(
     query: &str,
     type_param: &str,
     pool: &State<AnyPool>,
+    chains: &State<ChainRegistry>,
 ) -> status::Custom<RawJson<String>> {
-    if !matches!(type_param, "rpc" | "indexed") {
-        return status::Custom(
-            Status::BadRequest,
-            RawJson(
-                r#"{"error": "Invalid type. Supported values are: 'rpc' or 'indexed'."} "#
-                    .to_string(),
-            ),
-        );
-    }
-
-    let query = &utils::remove_sql_comments(query);
-
-    if !utils::is_query_only(query.to_owned()) {
-        return status::Custom(
-            Status::BadRequest,
-            RawJson(
-                r#"{"error": "Only SELECT queries are allowed. CREATE, DROP, INSERT, UPDATE, DELETE, and other write ops are blocked."} "#
-                    .to_string(),
-            ),
-        );
+    let validation = pipeline::validate(query, type_param, chains);
+    if let Some(error) = validation.errors.first() {
+        return match error.stage {
+            Stage::Parse => json_error(&error.message),
+            _ => json_response(Status::BadRequest, json!({ "error": error.message })),
+        };
     }
+    let query = &validation.normalized_query;
 
-    if type_param == "rpc" {
-        let (_label, result): (&str, Result<QueryResult, _>) = if utils::is_sui_rpc_query(query) {
+    if validation.engine != Some(Engine::Indexed) {
+        let (_label, result): (&str, Result<QueryResult, _>) = if validation.engine == Some(Engine::Sui) {
             let res = SuiQlInterpreter::run_program(query).await.map(QueryResult::Sui);
             ("SUI_QL", res)
         } else {
@@ -111,48 +153,706 @@ async fn run_query(
             Err(err) => json_error(err),
         }
     } else {
-        let flattened_query = utils::flatten_known_chain_tables(&query);
-        if let Err(e) = gluesql::prelude::parse(&flattened_query) {
-            return json_error(e);
-        }
+        let flattened_query = validation.sql.unwrap_or_default();
 
-        let rows_json: Vec<Value> = match sqlx::query(&flattened_query).fetch_all(&**pool).await {
-            Ok(rows) => rows.into_iter().map(|row| row_to_json(&row)).collect(),
+        let rows = match sqlx::query(&flattened_query).fetch_all(&**pool).await {
+            Ok(rows) => rows,
             Err(e) => return json_error(e),
         };
 
-        let wrapped_data: Vec<Value> = rows_json.into_iter().map(|row| json!(row)).collect();
+        // Exact decimals stay numbers here, as they always have for existing clients
+        let options = DecodeOptions {
+            decimal: DecimalFormat::Number,
+            ..Default::default()
+        };
+        let indexed = IndexedRows::new(&rows, Layout::Objects, options);
+        match serde_json::to_string(&WqlBody::new(indexed)) {
+            Ok(json) => status::Custom(Status::Ok, RawJson(json)),
+            Err(err) => json_error(err),
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/run",
+    params(
+        ("type_param" = String, Query, description = "Either `rpc` or `indexed`"),
+        ("query" = String, Query, description = "SuiQL, EQL or SQL query to run"),
+        QueryOptions,
+    ),
+    responses(
+        (status = 200, description = "Query results in the shared envelope", body = Envelope,
+            content_type = [
+                "application/json",
+                "application/msgpack",
+                "application/cbor",
+                "text/csv",
+                "text/tab-separated-values",
+                "application/vnd.apache.arrow.stream",
+                "application/vnd.apache.parquet",
+            ]),
+        (status = 400, description = "Query was rejected before execution", body = ErrorResponse),
+        (status = 500, description = "Query failed", body = ErrorResponse),
+    )
+)]
+#[get("/v1/run?<type_param>&<query>&<options..>")]
+#[allow(clippy::too_many_arguments)]
+async fn run_query_v1(
+    query: &str,
+    type_param: &str,
+    mut options: QueryOptions,
+    accept: Option<&Accept>,
+    request_id: RequestId,
+    pool: &State<AnyPool>,
+    chains: &State<ChainRegistry>,
+    schema: &State<SchemaCache>,
+    abis: &State<AbiRegistry>,
+    signatures: &State<SignatureRegistry>,
+) -> Result<Encoded, status::Custom<RawJson<String>>> {
+    options.negotiate(accept);
+    let validation = pipeline::validate(query, type_param, chains);
+    if let Some(error) = validation.errors.first() {
+        return Err(json_response(
+            Status::BadRequest,
+            json!({ "error": error.message }),
+        ));
+    }
+    if let Err(error) = options
+        .delimited()
+        .and(options.parquet())
+        .and(options.time_zone())
+    {
+        return Err(json_response(Status::BadRequest, json!({ "error": error })));
+    }
 
-        status::Custom(
-            Status::Ok,
-            RawJson(
-                json!({
-                    "type": "Wql",
-                    "data": [
-                        {
-                            "result": {
-                                "indexed": wrapped_data
-                            }
-                        }
-                    ]
-                })
-                .to_string(),
-            ),
+    let executed = execute(validation, options.row_limit(), pool)
+        .await
+        .map_err(json_error)?;
+    respond(
+        &executed,
+        &options,
+        &request_id,
+        pool,
+        chains,
+        schema,
+        abis,
+        signatures,
+    )
+    .await
+}
+
+/// A validated query and what it returned.
+pub struct ExecutedQuery {
+    engine: Engine,
+    validation: Validation,
+    output: QueryOutput,
+    elapsed_ms: u64,
+}
+
+impl ExecutedQuery {
+    pub fn row_count(&self) -> usize {
+        match &self.output {
+            QueryOutput::Interpreter(values) => values.len(),
+            QueryOutput::Indexed { rows, .. } => rows.len(),
+        }
+    }
+}
+
+/// Runs a validated query, fetching at most `limit` indexed rows.
+async fn execute(
+    validation: Validation,
+    limit: usize,
+    pool: &AnyPool,
+) -> Result<ExecutedQuery, String> {
+    let started = Instant::now();
+    let engine = validation.engine.unwrap_or(Engine::Eql);
+    let query = &validation.normalized_query;
+
+    let output = match engine {
+        Engine::Sui => SuiQlInterpreter::run_program(query)
+            .await
+            .map_err(|e| e.to_string())
+            .and_then(|results| to_values(&results)),
+        Engine::Eql => EQlInterpreter::run_program(query)
+            .await
+            .map_err(|e| e.to_string())
+            .and_then(|results| to_values(&results)),
+        Engine::Indexed => {
+            let sql = validation.sql.as_deref().unwrap_or_default();
+            let mut rows = sqlx::query(sql)
+                .fetch(pool)
+                .take(limit.saturating_add(1))
+                .try_collect::<Vec<_>>()
+                .await
+                .map_err(|e| e.to_string())?;
+            // Rows carry the column types but not their nullability, and without rows
+            // there are no columns to read at all
+            let described = pool
+                .describe(sql)
+                .await
+                .map_err(|e| log::warn!("Failed to describe query: {e}"))
+                .ok();
+            let truncated = rows.len() > limit;
+            rows.truncate(limit);
+            Ok(QueryOutput::Indexed {
+                rows,
+                described,
+                truncated,
+            })
+        }
+    }?;
+    Ok(ExecutedQuery {
+        engine,
+        validation,
+        output,
+        elapsed_ms: started.elapsed().as_millis() as u64,
+    })
+}
+
+fn to_values<T: Serialize>(results: &[T]) -> Result<QueryOutput, String> {
+    results
+        .iter()
+        .map(serde_json::to_value)
+        .collect::<Result<Vec<Value>, _>>()
+        .map(QueryOutput::Interpreter)
+        .map_err(|e| e.to_string())
+}
+
+/// Shapes an executed query into the response `options` ask for, decoding ABIs and
+/// adding signatures on the way.
+#[allow(clippy::too_many_arguments)]
+async fn respond(
+    executed: &ExecutedQuery,
+    options: &QueryOptions,
+    request_id: &RequestId,
+    pool: &AnyPool,
+    chains: &ChainRegistry,
+    schema: &SchemaCache,
+    abis: &AbiRegistry,
+    signatures: &SignatureRegistry,
+) -> Result<Encoded, status::Custom<RawJson<String>>> {
+    let engine = executed.engine;
+    let validation = &executed.validation;
+    let envelope = Envelope::new(engine, request_id);
+    let no_rows = Vec::new();
+
+    let (mut envelope, indexed_rows) = match &executed.output {
+        QueryOutput::Interpreter(values)
+            if engine == Engine::Eql && options.enriches_signatures() =>
+        {
+            let mut values = values.clone();
+            values.iter_mut().for_each(|v| signatures.enrich_value(v));
+            envelope
+                .with_interpreter_results(&values, options)
+                .map(|envelope| (envelope, &no_rows))
+                .map_err(|e| e.to_string())
+        }
+        QueryOutput::Interpreter(values) => envelope
+            .with_interpreter_results(values, options)
+            .map(|envelope| (envelope, &no_rows))
+            .map_err(|e| e.to_string()),
+        QueryOutput::Indexed {
+            rows,
+            described,
+            truncated,
+        } => envelope
+            .with_indexed_rows(rows, *truncated, described.as_ref(), options)
+            .map(|envelope| (envelope, rows)),
+    }
+    .map_err(json_error)?;
+    envelope
+        .warnings
+        .splice(0..0, validation.warnings.iter().cloned());
+    // The driver does not report numeric precision and scale, the catalog does
+    if engine == Engine::Indexed
+        && pool.any_kind() == AnyKind::Postgres
+        && envelope.columns.iter().any(|c| c.logical_type == "decimal")
+    {
+        let tables: Vec<&str> = validation.tables.iter().map(|t| t.table.as_str()).collect();
+        if let Some(select) = validation.sql.as_deref().and_then(pipeline::select_columns) {
+            match schema.decimal_types(pool, chains, &tables, &select).await {
+                Ok(declared) => envelope.declare_decimals(&declared),
+                Err(e) => log::warn!("Failed to read declared decimal types: {e}"),
+            }
+        }
+    }
+    let mut appended = Vec::new();
+    if options.decodes_abi() && engine != Engine::Indexed {
+        envelope
+            .warnings
+            .push("decode=abi only applies to indexed queries".to_string());
+    } else if options.decodes_abi() {
+        let contracts = abis.contracts(pool).await.map_err(json_error)?;
+        let evm_chains: Vec<&str> = validation
+            .tables
+            .iter()
+            .filter_map(|table| chains.get(table.table.split('.').next()?))
+            .filter(|chain| chain.family == ChainFamily::Evm)
+            .map(|chain| chain.id.as_str())
+            .collect();
+        let taken: Vec<String> = envelope.columns.iter().map(|c| c.name.clone()).collect();
+        let (columns, values) = abi::decode_rows(
+            indexed_rows,
+            &evm_chains,
+            &contracts,
+            &taken,
+            &options.decode_options(),
+        );
+        envelope.append_columns(columns, &values);
+        appended = values;
+    }
+    if options.enriches_signatures() && engine == Engine::Sui {
+        envelope
+            .warnings
+            .push("enrich=signatures only applies to indexed and EQL queries".to_string());
+    } else if options.enriches_signatures() && engine == Engine::Indexed {
+        let taken: Vec<String> = envelope.columns.iter().map(|c| c.name.clone()).collect();
+        let (columns, values) =
+            signatures.enrich_rows(indexed_rows, &taken, &options.decode_options());
+        envelope.append_columns(columns, &values);
+        appended.extend(values);
+    }
+    envelope.elapsed_ms = executed.elapsed_ms;
+    response::encode(&envelope, indexed_rows, &appended, options).map_err(json_error)
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/jobs",
+    request_body = JobRequest,
+    responses(
+        (status = 202, description = "Query is running in the background", body = Job),
+        (status = 400, description = "Query was rejected before execution", body = ErrorResponse),
+        (status = 429, description = "`JOB_MAX_RUNNING` jobs are already running", body = ErrorResponse),
+        (status = 503, description = "`JOB_MAX_STORED` jobs are running or holding results", body = ErrorResponse),
+    )
+)]
+#[post("/v1/jobs", data = "<request>")]
+fn submit_job(
+    request: Json<JobRequest>,
+    pool: &State<AnyPool>,
+    chains: &State<ChainRegistry>,
+    jobs: &State<JobStore>,
+) -> status::Custom<RawJson<String>> {
+    let validation = pipeline::validate(&request.query, &request.type_param, chains);
+    if let Some(error) = validation.errors.first() {
+        return json_response(Status::BadRequest, json!({ "error": error.message }));
+    }
+    let limit = QueryOptions {
+        limit: request.limit,
+        ..Default::default()
+    }
+    .row_limit();
+    let job = match jobs.submit() {
+        Ok(job) => job,
+        Err(rejection) => {
+            return json_response(rejection.status(), json!({ "error": rejection.message() }))
+        }
+    };
+    let (id, jobs, pool) = (job.id.clone(), jobs.inner().clone(), pool.inner().clone());
+    tokio::spawn(async move {
+        let result = execute(validation, limit, &pool).await;
+        jobs.finish(&id, result);
+    });
+    json_response(Status::Accepted, job)
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/jobs/{id}",
+    params(("id" = String, Path, description = "Job id returned by `POST /v1/jobs`")),
+    responses(
+        (status = 200, description = "Job status", body = Job),
+        (status = 404, description = "Unknown or expired job", body = ErrorResponse),
+    )
+)]
+#[get("/v1/jobs/<id>")]
+fn get_job(id: &str, jobs: &State<JobStore>) -> status::Custom<RawJson<String>> {
+    match jobs.get(id) {
+        Some(job) => json_response(Status::Ok, job),
+        None => json_response(
+            Status::NotFound,
+            json!({ "error": format!("Unknown job '{}'", id) }),
+        ),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/jobs/{id}/result",
+    params(
+        ("id" = String, Path, description = "Job id returned by `POST /v1/jobs`"),
+        QueryOptions,
+    ),
+    responses(
+        (status = 200, description = "Job results in the shared envelope, in any `/v1/run` format; `limit` is fixed when the job is submitted", body = Envelope,
+            content_type = [
+                "application/json",
+                "application/msgpack",
+                "application/cbor",
+                "text/csv",
+                "text/tab-separated-values",
+                "application/vnd.apache.arrow.stream",
+                "application/vnd.apache.parquet",
+            ]),
+        (status = 400, description = "Invalid response options", body = ErrorResponse),
+        (status = 404, description = "Unknown or expired job", body = ErrorResponse),
+        (status = 409, description = "Job is still running", body = ErrorResponse),
+        (status = 500, description = "Job failed", body = ErrorResponse),
+    )
+)]
+#[get("/v1/jobs/<id>/result?<options..>")]
+#[allow(clippy::too_many_arguments)]
+async fn get_job_result(
+    id: &str,
+    mut options: QueryOptions,
+    accept: Option<&Accept>,
+    request_id: RequestId,
+    pool: &State<AnyPool>,
+    chains: &State<ChainRegistry>,
+    schema: &State<SchemaCache>,
+    abis: &State<AbiRegistry>,
+    signatures: &State<SignatureRegistry>,
+    jobs: &State<JobStore>,
+) -> Result<Encoded, status::Custom<RawJson<String>>> {
+    options.negotiate(accept);
+    if let Err(error) = options
+        .delimited()
+        .and(options.parquet())
+        .and(options.time_zone())
+    {
+        return Err(json_response(Status::BadRequest, json!({ "error": error })));
+    }
+    match jobs.result(id) {
+        Some((_, Some(executed))) => {
+            respond(
+                &executed,
+                &options,
+                &request_id,
+                pool,
+                chains,
+                schema,
+                abis,
+                signatures,
+            )
+            .await
+        }
+        Some((job, None)) if job.status == JobStatus::Running => Err(json_response(
+            Status::Conflict,
+            json!({ "error": format!("Job '{}' is still running", id) }),
+        )),
+        Some((job, None)) => Err(json_error(job.error.unwrap_or_default())),
+        None => Err(json_response(
+            Status::NotFound,
+            json!({ "error": format!("Unknown job '{}'", id) }),
+        )),
+    }
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct ValidateRequest {
+    query: String,
+    #[serde(rename = "type")]
+    type_param: String,
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/validate",
+    request_body = ValidateRequest,
+    responses((status = 200, description = "What the server would do with the query", body = Validation))
+)]
+#[post("/v1/validate", data = "<request>")]
+fn validate_query(
+    request: Json<ValidateRequest>,
+    chains: &State<ChainRegistry>,
+) -> status::Custom<RawJson<String>> {
+    json_response(
+        Status::Ok,
+        pipeline::validate(&request.query, &request.type_param, chains),
+    )
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct ExplainRequest {
+    query: String,
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/explain",
+    request_body = ExplainRequest,
+    responses(
+        (status = 200, description = "Estimated query plan", body = Explanation),
+        (status = 400, description = "Query was rejected before planning", body = ErrorResponse),
+        (status = 500, description = "Planning failed", body = ErrorResponse),
+    )
+)]
+#[post("/v1/explain", data = "<request>")]
+async fn explain_query(
+    request: Json<ExplainRequest>,
+    pool: &State<AnyPool>,
+    chains: &State<ChainRegistry>,
+) -> status::Custom<RawJson<String>> {
+    let validation = pipeline::validate(&request.query, "indexed", chains);
+    if let Some(error) = validation.errors.first() {
+        return json_response(Status::BadRequest, json!({ "error": error.message }));
+    }
+    let sql = validation.sql.as_deref().unwrap_or_default();
+    match explain::explain(pool, sql, &validation.tables).await {
+        Ok(explanation) => json_response(Status::Ok, explanation),
+        Err(err) => json_error(err),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/chains",
+    responses((status = 200, description = "Chain registry", body = ChainsResponse))
+)]
+#[get("/v1/chains")]
+fn get_chains(chains: &State<ChainRegistry>) -> status::Custom<RawJson<String>> {
+    json_response(
+        Status::Ok,
+        ChainsResponse {
+            chains: chains.chains().to_vec(),
+        },
+    )
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/schema",
+    params(("refresh" = Option<bool>, Query, description = "Reload the catalog instead of using the cache")),
+    responses(
+        (status = 200, description = "Exposed chain tables", body = TablesResponse),
+        (status = 500, description = "Catalog could not be read", body = ErrorResponse),
+    )
+)]
+#[get("/v1/schema?<refresh>")]
+async fn get_schema(
+    refresh: Option<bool>,
+    pool: &State<AnyPool>,
+    schema: &State<SchemaCache>,
+    chains: &State<ChainRegistry>,
+) -> status::Custom<RawJson<String>> {
+    match schema.tables(pool, chains, refresh.unwrap_or(false)).await {
+        Ok(tables) => json_response(Status::Ok, TablesResponse { tables }),
+        Err(err) => json_error(err),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/schema/{chain}/{table}",
+    params(
+        ("chain" = String, Path, description = "Chain id or alias, e.g. `eth`"),
+        ("table" = String, Path, description = "Table name, e.g. `blocks`"),
+        ("refresh" = Option<bool>, Query, description = "Reload the catalog instead of using the cache"),
+    ),
+    responses(
+        (status = 200, description = "Table columns and row estimate", body = TableSchema),
+        (status = 404, description = "Unknown table", body = ErrorResponse),
+        (status = 500, description = "Catalog could not be read", body = ErrorResponse),
+    )
+)]
+#[get("/v1/schema/<chain>/<table>?<refresh>")]
+async fn get_table_schema(
+    chain: &str,
+    table: &str,
+    refresh: Option<bool>,
+    pool: &State<AnyPool>,
+    schema: &State<SchemaCache>,
+    chains: &State<ChainRegistry>,
+) -> status::Custom<RawJson<String>> {
+    match schema
+        .table(pool, chains, chain, table, refresh.unwrap_or(false))
+        .await
+    {
+        Ok(Some(table)) => json_response(Status::Ok, table),
+        Ok(None) => json_response(
+            Status::NotFound,
+            json!({ "error": format!("Unknown table '{}.{}'", chain, table) }),
+        ),
+        Err(err) => json_error(err),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/abis",
+    request_body = AbiRegistration,
+    responses(
+        (status = 201, description = "ABI stored for `decode=abi`", body = AbiRegistration),
+        (status = 400, description = "Unknown chain, invalid address or invalid ABI", body = ErrorResponse),
+        (status = 401, description = "Missing or invalid `Authorization: Bearer <ADMIN_API_KEY>`", body = ErrorResponse),
+        (status = 403, description = "Registration is disabled because `ADMIN_API_KEY` is not set", body = ErrorResponse),
+        (status = 500, description = "ABI could not be stored", body = ErrorResponse),
+    )
+)]
+#[post("/v1/abis", data = "<request>")]
+async fn register_abi(
+    admin: Result<Admin, AdminError>,
+    request: Json<AbiRegistration>,
+    pool: &State<AnyPool>,
+    chains: &State<ChainRegistry>,
+    abis: &State<AbiRegistry>,
+) -> status::Custom<RawJson<String>> {
+    if let Err(error) = admin {
+        return json_response(error.status(), json!({ "error": error.message() }));
+    }
+    let chain = match chains.get(&request.chain) {
+        Some(chain) if chain.family == ChainFamily::Evm => chain,
+        _ => {
+            return json_response(
+                Status::BadRequest,
+                json!({ "error": format!("Unknown EVM chain '{}'", request.chain) }),
+            )
+        }
+    };
+    let address = match abi::normalize_address(&request.address) {
+        Ok(address) => address,
+        Err(error) => return json_response(Status::BadRequest, json!({ "error": error })),
+    };
+    if let Err(error) = abi::parse_abi(&request.abi) {
+        return json_response(Status::BadRequest, json!({ "error": error }));
+    }
+    match abis.register(pool, &chain.id, &address, &request.abi).await {
+        Ok(()) => json_response(
+            Status::Created,
+            AbiRegistration {
+                chain: chain.id.clone(),
+                address,
+                abi: request.into_inner().abi,
+            },
+        ),
+        Err(err) => json_error(err),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/abis/{chain}/{address}",
+    params(
+        ("chain" = String, Path, description = "Chain id or alias, e.g. `eth`"),
+        ("address" = String, Path, description = "Contract address"),
+    ),
+    responses(
+        (status = 200, description = "Registered ABI", body = AbiRegistration),
+        (status = 404, description = "No ABI registered for the contract", body = ErrorResponse),
+        (status = 500, description = "Registry could not be read", body = ErrorResponse),
+    )
+)]
+#[get("/v1/abis/<chain>/<address>")]
+async fn get_abi(
+    chain: &str,
+    address: &str,
+    pool: &State<AnyPool>,
+    chains: &State<ChainRegistry>,
+    abis: &State<AbiRegistry>,
+) -> status::Custom<RawJson<String>> {
+    let not_found = || {
+        json_response(
+            Status::NotFound,
+            json!({ "error": format!("No ABI registered for {} on '{}'", address, chain) }),
         )
+    };
+    let (Some(known), Ok(normalized)) = (chains.get(chain), abi::normalize_address(address))
+    else {
+        return not_found();
+    };
+    match abis.get(pool, &known.id, &normalized).await {
+        Ok(Some(abi)) => json_response(
+            Status::Ok,
+            AbiRegistration {
+                chain: known.id.clone(),
+                address: normalized,
+                abi,
+            },
+        ),
+        Ok(None) => not_found(),
+        Err(err) => json_error(err),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/signatures/{hash}",
+    params(("hash" = String, Path, description = "4-byte function selector or 32-byte event topic, in hex")),
+    responses(
+        (status = 200, description = "Matching signatures; several when the hash is ambiguous", body = SignaturesResponse),
+        (status = 400, description = "Not a selector or topic hash", body = ErrorResponse),
+        (status = 404, description = "No known signature has this hash", body = ErrorResponse),
+    )
+)]
+#[get("/v1/signatures/<hash>")]
+fn get_signatures(
+    hash: &str,
+    signatures: &State<SignatureRegistry>,
+) -> status::Custom<RawJson<String>> {
+    match signatures.lookup(hash) {
+        Ok(found) if found.is_empty() => json_response(
+            Status::NotFound,
+            json!({ "error": format!("No known signature for {}", hash) }),
+        ),
+        Ok(found) => json_response(
+            Status::Ok,
+            SignaturesResponse {
+                hash: hash.to_lowercase(),
+                signatures: found,
+            },
+        ),
+        Err(error) => json_response(Status::BadRequest, json!({ "error": error })),
     }
 }
 
+#[utoipa::path(
+    get,
+    path = "/openapi.json",
+    responses((status = 200, description = "This OpenAPI document", body = Value))
+)]
+#[get("/openapi.json")]
+fn openapi_spec() -> RawJson<String> {
+    RawJson(openapi::spec_json())
+}
+
 #[options("/<_..>")]
 fn preflight_handler() -> &'static str {
     ""
 }
 
+/// Every route the server mounts; the OpenAPI tests check the spec against this list.
+pub fn api_routes() -> Vec<rocket::Route> {
+    routes![
+        index,
+        run_query,
+        run_query_v1,
+        submit_job,
+        get_job,
+        get_job_result,
+        health,
+        validate_query,
+        explain_query,
+        get_chains,
+        get_schema,
+        get_table_schema,
+        register_abi,
+        get_abi,
+        get_signatures,
+        openapi_spec,
+        preflight_handler
+    ]
+}
+
 #[rocket::main]
 async fn main() -> Result<(), rocket::Error> {
     // CryptoProvider::install_default();
 
     dotenv().ok();
 
+    let chains = ChainRegistry::from_env().expect("Could not load chain registry");
+
     let db_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
     println!("Connecting to DB: {}", db_url);
 
@@ -160,10 +860,22 @@ async fn main() -> Result<(), rocket::Error> {
         .await
         .expect("Could not connect to DB");
 
+    let abis = AbiRegistry::from_env();
+    if let Err(e) = abis.create_table(&pool).await {
+        log::warn!("Could not create the ABI registry table, decode=abi is unavailable: {e}");
+    }
+
     rocket::build()
         .manage(pool)
+        .manage(chains)
+        .manage(SchemaCache::from_env())
+        .manage(JobStore::from_env())
+        .manage(abis)
+        .manage(AdminKey::from_env())
+        .manage(SignatureRegistry::from_env().expect("Could not load signature database"))
         .attach(CORS)
-        .mount("/", routes![index, run_query, health, preflight_handler])
+        .attach(Compression::from_env())
+        .mount("/", api_routes())
         .launch()
         .await?;
 
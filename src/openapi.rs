@@ -0,0 +1,171 @@
+use utoipa::OpenApi;
+
+use crate::abi::AbiRegistration;
+use crate::chains::{Availability, Chain, ChainFamily, ChainsResponse};
+use crate::explain::{Explanation, PlanSummary};
+use crate::jobs::{Job, JobRequest, JobStatus};
+use crate::pipeline::{Engine, PipelineError, ResolvedTable, Stage, Validation};
+use crate::response::{Column, Envelope};
+use crate::schema::{ColumnSchema, TableSchema, TablesResponse};
+use crate::signatures::{Signature, SignatureKind, SignaturesResponse};
+use crate::sql_to_json::DecodeWarning;
+use crate::utils::ErrorResponse;
+use crate::{ExplainRequest, ValidateRequest};
+
+/// OpenAPI 3 document built from the `#[utoipa::path]` annotations on the Rocket handlers.
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "Sandworm API", description = "Query Sui, EVM chains and indexed chain tables"),
+    paths(
+        crate::index,
+        crate::health,
+        crate::run_query,
+        crate::run_query_v1,
+        crate::submit_job,
+        crate::get_job,
+        crate::get_job_result,
+        crate::validate_query,
+        crate::explain_query,
+        crate::get_chains,
+        crate::get_schema,
+        crate::get_table_schema,
+        crate::register_abi,
+        crate::get_abi,
+        crate::get_signatures,
+        crate::openapi_spec,
+    ),
+    components(schemas(
+        ErrorResponse,
+        Envelope,
+        Column,
+        DecodeWarning,
+        ValidateRequest,
+        ExplainRequest,
+        JobRequest,
+        Job,
+        JobStatus,
+        Validation,
+        Engine,
+        Stage,
+        PipelineError,
+        ResolvedTable,
+        Explanation,
+        PlanSummary,
+        ChainsResponse,
+        Chain,
+        ChainFamily,
+        Availability,
+        TablesResponse,
+        TableSchema,
+        ColumnSchema,
+        AbiRegistration,
+        SignaturesResponse,
+        Signature,
+        SignatureKind,
+    ))
+)]
+pub struct ApiDoc;
+
+pub fn spec_json() -> String {
+    ApiDoc::openapi()
+        .to_pretty_json()
+        .unwrap_or_else(|e| format!(r#"{{"error": "Could not render OpenAPI document: {}"}}"#, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{spec_json, ApiDoc};
+    use regex::Regex;
+    use rocket::http::Method;
+    use utoipa::openapi::PathItemType;
+    use utoipa::OpenApi;
+
+    /// Rocket writes `/v1/schema/<chain>/<table>`, OpenAPI writes `/v1/schema/{chain}/{table}`.
+    fn openapi_path(rocket_path: &str) -> String {
+        let re = Regex::new(r"<([A-Za-z_][A-Za-z0-9_]*)>").unwrap();
+        re.replace_all(rocket_path, "{$1}").into_owned()
+    }
+
+    fn path_item_type(method: Method) -> Option<PathItemType> {
+        match method {
+            Method::Get => Some(PathItemType::Get),
+            Method::Post => Some(PathItemType::Post),
+            Method::Put => Some(PathItemType::Put),
+            Method::Patch => Some(PathItemType::Patch),
+            Method::Delete => Some(PathItemType::Delete),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_every_route_is_documented() {
+        let spec = ApiDoc::openapi();
+        let mut missing = Vec::new();
+        for route in crate::api_routes() {
+            // The CORS preflight catch-all is not part of the API
+            let Some(item_type) = path_item_type(route.method) else {
+                continue;
+            };
+            let path = openapi_path(route.uri.path());
+            let documented = spec
+                .paths
+                .paths
+                .get(&path)
+                .is_some_and(|item| item.operations.contains_key(&item_type));
+            if !documented {
+                missing.push(format!("{} {}", route.method, path));
+            }
+        }
+        assert!(
+            missing.is_empty(),
+            "Routes missing from the OpenAPI document: {:?}",
+            missing
+        );
+    }
+
+    #[test]
+    fn test_every_documented_path_is_routed() {
+        let routed: Vec<String> = crate::api_routes()
+            .iter()
+            .map(|route| openapi_path(route.uri.path()))
+            .collect();
+        for path in ApiDoc::openapi().paths.paths.keys() {
+            assert!(
+                routed.contains(path),
+                "OpenAPI document describes {} but no route serves it",
+                path
+            );
+        }
+    }
+
+    /// `openapi.json` is the published contract; parameters, bodies and schemas must not
+    /// drift from it unnoticed. Run with `UPDATE_OPENAPI=1` to regenerate it.
+    #[test]
+    fn test_checked_in_spec_is_current() {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/openapi.json");
+        let generated = spec_json() + "\n";
+        if std::env::var_os("UPDATE_OPENAPI").is_some() {
+            std::fs::write(path, &generated).unwrap();
+            return;
+        }
+        let checked_in = std::fs::read_to_string(path).unwrap_or_default();
+        if let Some((line, (expected, actual))) = checked_in
+            .lines()
+            .zip(generated.lines())
+            .enumerate()
+            .find(|(_, (expected, actual))| expected != actual)
+        {
+            panic!(
+                "openapi.json differs from the generated spec at line {}:\n  checked in: {}\n  generated:  {}\nRegenerate it with UPDATE_OPENAPI=1 cargo test",
+                line + 1,
+                expected.trim(),
+                actual.trim()
+            );
+        }
+        assert_eq!(
+            checked_in.lines().count(),
+            generated.lines().count(),
+            "openapi.json is out of date; regenerate it with UPDATE_OPENAPI=1 cargo test"
+        );
+    }
+}
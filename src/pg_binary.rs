@@ -0,0 +1,679 @@
+//! Decoders for Postgres binary wire formats that `sqlx::Any` cannot decode itself.
+//! Values are read as raw bytes (`Vec<u8>`) and parsed here.
+
+use std::fmt::Write;
+
+use chrono::{Duration, NaiveDate, NaiveTime};
+use serde_json::{Map, Value};
+use sqlx::any::{AnyTypeInfo, AnyTypeInfoKind};
+use sqlx::error::BoxDynError;
+use sqlx::postgres::{PgTypeInfo, PgTypeKind};
+use sqlx::TypeInfo;
+
+use crate::sql_to_json::DecodeOptions;
+
+const NUMERIC_POS: u16 = 0x0000;
+const NUMERIC_NEG: u16 = 0x4000;
+const NUMERIC_NAN: u16 = 0xC000;
+const NUMERIC_PINF: u16 = 0xD000;
+const NUMERIC_NINF: u16 = 0xF000;
+
+/// Big-endian cursor over a binary value.
+pub struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Reader { bytes }
+    }
+
+    pub fn take(&mut self, len: usize) -> Result<&'a [u8], BoxDynError> {
+        if self.bytes.len() < len {
+            return Err(format!(
+                "unexpected end of binary value: needed {} bytes, {} left",
+                len,
+                self.bytes.len()
+            )
+            .into());
+        }
+        let (head, tail) = self.bytes.split_at(len);
+        self.bytes = tail;
+        Ok(head)
+    }
+
+    pub fn i16(&mut self) -> Result<i16, BoxDynError> {
+        Ok(i16::from_be_bytes(self.take(2)?.try_into()?))
+    }
+
+    pub fn u16(&mut self) -> Result<u16, BoxDynError> {
+        Ok(u16::from_be_bytes(self.take(2)?.try_into()?))
+    }
+
+    pub fn i32(&mut self) -> Result<i32, BoxDynError> {
+        Ok(i32::from_be_bytes(self.take(4)?.try_into()?))
+    }
+
+    pub fn u32(&mut self) -> Result<u32, BoxDynError> {
+        Ok(u32::from_be_bytes(self.take(4)?.try_into()?))
+    }
+
+    pub fn i64(&mut self) -> Result<i64, BoxDynError> {
+        Ok(i64::from_be_bytes(self.take(8)?.try_into()?))
+    }
+
+    /// A length-prefixed value as used inside arrays and records; `None` for SQL NULL.
+    pub fn value(&mut self) -> Result<Option<&'a [u8]>, BoxDynError> {
+        match self.i32()? {
+            -1 => Ok(None),
+            len if len < 0 => Err(format!("invalid value length {}", len).into()),
+            len => Ok(Some(self.take(len as usize)?)),
+        }
+    }
+}
+
+/// Type OIDs from `pg_type.dat` that are decoded inside arrays and records.
+mod oid {
+    pub const BOOL: u32 = 16;
+    pub const BYTEA: u32 = 17;
+    pub const CHAR: u32 = 18;
+    pub const NAME: u32 = 19;
+    pub const INT8: u32 = 20;
+    pub const INT2: u32 = 21;
+    pub const INT4: u32 = 23;
+    pub const TEXT: u32 = 25;
+    pub const OID: u32 = 26;
+    pub const JSON: u32 = 114;
+    pub const XML: u32 = 142;
+    pub const FLOAT4: u32 = 700;
+    pub const FLOAT8: u32 = 701;
+    pub const UNKNOWN: u32 = 705;
+    pub const BPCHAR: u32 = 1042;
+    pub const VARCHAR: u32 = 1043;
+    pub const DATE: u32 = 1082;
+    pub const TIME: u32 = 1083;
+    pub const TIMESTAMP: u32 = 1114;
+    pub const TIMESTAMPTZ: u32 = 1184;
+    pub const NUMERIC: u32 = 1700;
+    pub const RECORD: u32 = 2249;
+    pub const UUID: u32 = 2950;
+    pub const JSONB: u32 = 3802;
+    pub const CIDR: u32 = 650;
+    pub const MACADDR8: u32 = 774;
+    pub const MONEY: u32 = 790;
+    pub const MACADDR: u32 = 829;
+    pub const INET: u32 = 869;
+    pub const INTERVAL: u32 = 1186;
+    pub const TIMETZ: u32 = 1266;
+
+    /// Scalar types with a decoder in [`super::to_json`].
+    pub const SCALARS: &[u32] = &[
+        BOOL,
+        BYTEA,
+        CHAR,
+        NAME,
+        INT8,
+        INT2,
+        INT4,
+        TEXT,
+        OID,
+        JSON,
+        XML,
+        FLOAT4,
+        FLOAT8,
+        UNKNOWN,
+        BPCHAR,
+        VARCHAR,
+        DATE,
+        TIME,
+        TIMESTAMP,
+        TIMESTAMPTZ,
+        NUMERIC,
+        UUID,
+        JSONB,
+        CIDR,
+        MACADDR8,
+        MONEY,
+        MACADDR,
+        INET,
+        INTERVAL,
+        TIMETZ,
+    ];
+
+    /// Array types, whose elements carry their own OID on the wire.
+    pub const ARRAYS: &[u32] = &[
+        143, 199, 651, 775, 791, 1000, 1001, 1002, 1003, 1005, 1007, 1009, 1014, 1015, 1016, 1021,
+        1022, 1028, 1040, 1041, 1115, 1182, 1183, 1185, 1187, 1231, 1270, 2287, 2951, 3807,
+    ];
+}
+
+fn postgres_epoch() -> NaiveDate {
+    NaiveDate::from_ymd_opt(2000, 1, 1).unwrap()
+}
+
+/// Whether values of this Postgres type can be decoded, rather than passed through as
+/// encoded wire bytes.
+pub fn is_supported(pg: &PgTypeInfo) -> bool {
+    match pg.kind() {
+        PgTypeKind::Array(_) | PgTypeKind::Composite(_) | PgTypeKind::Enum(_) => true,
+        PgTypeKind::Domain(base) => is_supported(base),
+        _ => pg.oid().is_some_and(|type_oid| {
+            let type_oid = type_oid.0;
+            oid::SCALARS.contains(&type_oid)
+                || oid::ARRAYS.contains(&type_oid)
+                || type_oid == oid::RECORD
+        }),
+    }
+}
+
+/// Logical type of Postgres arrays, composites, domains and the other types whose name
+/// alone does not say what they decode to; `None` for the rest.
+pub fn logical_type(pg: &PgTypeInfo) -> Option<String> {
+    let postgres = |pg: &PgTypeInfo| AnyTypeInfo(AnyTypeInfoKind::Postgres(pg.clone()));
+    match pg.kind() {
+        PgTypeKind::Array(element) => Some(format!(
+            "array<{}>",
+            crate::sql_to_json::logical_type(&postgres(element))
+        )),
+        PgTypeKind::Composite(_) => Some("json".to_string()),
+        PgTypeKind::Enum(_) => Some("text".to_string()),
+        PgTypeKind::Domain(base) => Some(crate::sql_to_json::logical_type(&postgres(base))),
+        _ => match pg.oid()?.0 {
+            oid::RECORD | oid::INTERVAL => Some("json".to_string()),
+            _ => None,
+        },
+    }
+}
+
+/// Decodes a scalar Postgres value of any type from its binary form. Enum labels are
+/// returned as text and domains as their base type; see [`to_json`] for the rest.
+pub fn value_to_json(
+    pg: &PgTypeInfo,
+    bytes: &[u8],
+    options: &DecodeOptions,
+) -> Result<Value, BoxDynError> {
+    match pg.kind() {
+        PgTypeKind::Enum(_) => Ok(Value::String(utf8(bytes)?)),
+        PgTypeKind::Domain(base) => value_to_json(base, bytes, options),
+        _ => match pg.oid() {
+            Some(type_oid) => to_json(type_oid.0, bytes, options),
+            None => Ok(Value::String(options.binary.encode(bytes))),
+        },
+    }
+}
+
+/// Whether `type_info` is a Postgres array, record or composite type, which are decoded
+/// from their binary form by [`container_to_json`].
+pub fn is_container(type_info: &AnyTypeInfo) -> bool {
+    let AnyTypeInfo(AnyTypeInfoKind::Postgres(pg)) = type_info else {
+        return false;
+    };
+    matches!(pg.kind(), PgTypeKind::Array(_) | PgTypeKind::Composite(_))
+        || pg.name() == "RECORD"
+        || pg.name().ends_with("[]")
+}
+
+/// Decodes a binary array, record or composite value. Composite fields are named as
+/// the driver describes them; anonymous records use Postgres' own `f1`, `f2`, ... names.
+pub fn container_to_json(
+    type_info: &AnyTypeInfo,
+    bytes: &[u8],
+    options: &DecodeOptions,
+) -> Result<Value, BoxDynError> {
+    if let AnyTypeInfo(AnyTypeInfoKind::Postgres(pg)) = type_info {
+        if let PgTypeKind::Composite(fields) = pg.kind() {
+            let names: Vec<&str> = fields.iter().map(|(name, _)| name.as_str()).collect();
+            return record_to_json(bytes, Some(&names), options);
+        }
+        if pg.name() == "RECORD" {
+            return record_to_json(bytes, None, options);
+        }
+    }
+    array_to_json(bytes, options)
+}
+
+/// Decodes a binary array of any dimension into nested JSON arrays.
+pub fn array_to_json(bytes: &[u8], options: &DecodeOptions) -> Result<Value, BoxDynError> {
+    let mut reader = Reader::new(bytes);
+    let ndim = reader.i32()?;
+    let _has_nulls = reader.i32()?;
+    let element_oid = reader.u32()?;
+    if ndim == 0 {
+        return Ok(Value::Array(Vec::new()));
+    }
+    let dims = (0..ndim)
+        .map(|_| {
+            let len = reader.i32()?;
+            let _lower_bound = reader.i32()?;
+            usize::try_from(len)
+                .map_err(|_| BoxDynError::from(format!("invalid array dimension {}", len)))
+        })
+        .collect::<Result<Vec<usize>, _>>()?;
+
+    fn nest(
+        dims: &[usize],
+        reader: &mut Reader<'_>,
+        element_oid: u32,
+        options: &DecodeOptions,
+    ) -> Result<Value, BoxDynError> {
+        let (len, inner) = dims.split_first().expect("at least one dimension");
+        let items = (0..*len)
+            .map(|_| match inner {
+                [] => match reader.value()? {
+                    Some(element) => to_json(element_oid, element, options),
+                    None => Ok(Value::Null),
+                },
+                _ => nest(inner, reader, element_oid, options),
+            })
+            .collect::<Result<Vec<Value>, _>>()?;
+        Ok(Value::Array(items))
+    }
+    nest(&dims, &mut reader, element_oid, options)
+}
+
+/// Decodes a binary record into an object keyed by `names`, or `f1`, `f2`, ... when the
+/// field names are not known.
+pub fn record_to_json(
+    bytes: &[u8],
+    names: Option<&[&str]>,
+    options: &DecodeOptions,
+) -> Result<Value, BoxDynError> {
+    let mut reader = Reader::new(bytes);
+    let count = reader.i32()?;
+    let mut record = Map::new();
+    for index in 0..count.max(0) as usize {
+        let field_oid = reader.u32()?;
+        let value = match reader.value()? {
+            Some(field) => to_json(field_oid, field, options)?,
+            None => Value::Null,
+        };
+        let name = match names.and_then(|names| names.get(index)) {
+            Some(name) => name.to_string(),
+            None => format!("f{}", index + 1),
+        };
+        record.insert(name, value);
+    }
+    Ok(Value::Object(record))
+}
+
+fn utf8(bytes: &[u8]) -> Result<String, BoxDynError> {
+    Ok(std::str::from_utf8(bytes)?.to_string())
+}
+
+/// Decodes one binary value of the type identified by `type_oid`. Types without a decoder
+/// here come back as their wire bytes, encoded like `BYTEA`.
+pub fn to_json(type_oid: u32, bytes: &[u8], options: &DecodeOptions) -> Result<Value, BoxDynError> {
+    let mut reader = Reader::new(bytes);
+    Ok(match type_oid {
+        oid::BOOL => Value::Bool(reader.take(1)?[0] != 0),
+        oid::INT2 => reader.i16()?.into(),
+        oid::INT4 => reader.i32()?.into(),
+        oid::OID => reader.u32()?.into(),
+        oid::INT8 => options.bigint.to_json(reader.i64()?),
+        oid::FLOAT4 => f64::from(f32::from_be_bytes(reader.take(4)?.try_into()?)).into(),
+        oid::FLOAT8 => f64::from_be_bytes(reader.take(8)?.try_into()?).into(),
+        oid::NUMERIC => options.decimal.to_json(numeric_to_string(bytes)?),
+        oid::CHAR
+        | oid::NAME
+        | oid::TEXT
+        | oid::XML
+        | oid::UNKNOWN
+        | oid::BPCHAR
+        | oid::VARCHAR => Value::String(utf8(bytes)?),
+        oid::JSON => serde_json::from_slice(bytes)?,
+        // jsonb is prefixed with a format version byte
+        oid::JSONB => match bytes.split_first() {
+            Some((&1, json)) => serde_json::from_slice(json)?,
+            _ => return Err("unsupported jsonb format version".into()),
+        },
+        oid::BYTEA => Value::String(options.binary.encode(bytes)),
+        oid::UUID => Value::String(uuid::Uuid::from_slice(bytes)?.hyphenated().to_string()),
+        oid::DATE => {
+            let date = postgres_epoch() + Duration::days(i64::from(reader.i32()?));
+            options.time_format.date_to_json(date)
+        }
+        oid::TIME => {
+            let time = NaiveTime::MIN + Duration::microseconds(reader.i64()?);
+            options.time_format.time_to_json(time)
+        }
+        oid::TIMESTAMP | oid::TIMESTAMPTZ => {
+            let timestamp = postgres_epoch().and_hms_opt(0, 0, 0).unwrap()
+                + Duration::microseconds(reader.i64()?);
+            options
+                .time_format
+                .instant_to_json(timestamp.and_utc().fixed_offset(), options.tz)
+        }
+        oid::TIMETZ => {
+            // The zone offset that follows is dropped, as for top-level TIMETZ values
+            let time = NaiveTime::MIN + Duration::microseconds(reader.i64()?);
+            options.time_format.time_to_json(time)
+        }
+        oid::INTERVAL => {
+            let micros = reader.i64()?;
+            let days = reader.i32()?;
+            let months = reader.i32()?;
+            interval_to_json(months, days, micros)
+        }
+        oid::INET | oid::CIDR => Value::String(inet_to_string(bytes, type_oid == oid::CIDR)?),
+        oid::MACADDR | oid::MACADDR8 => Value::String(mac_to_string(bytes)),
+        oid::MONEY => options.decimal.to_json(money_to_string(reader.i64()?)),
+        oid::RECORD => record_to_json(bytes, None, options)?,
+        array if oid::ARRAYS.contains(&array) => array_to_json(bytes, options)?,
+        // Not guessed at: callers flag these columns through `is_supported`
+        _ => Value::String(options.binary.encode(bytes)),
+    })
+}
+
+/// An interval as an ISO 8601 duration plus the three fields Postgres stores, which
+/// cannot be normalized into each other (a month is not a fixed number of days).
+pub fn interval_to_json(months: i32, days: i32, micros: i64) -> Value {
+    serde_json::json!({
+        "iso": iso_duration(months, days, micros),
+        "months": months,
+        "days": days,
+        "microseconds": micros,
+    })
+}
+
+/// ISO 8601 duration with a sign on each negative component, as Postgres writes it with
+/// `IntervalStyle = iso_8601`, e.g. `P1Y2M3DT4H5M6.5S` or `PT-1.5S`.
+fn iso_duration(months: i32, days: i32, micros: i64) -> String {
+    let mut out = String::from("P");
+    let (years, months) = (months / 12, months % 12);
+    for (value, unit) in [
+        (i64::from(years), 'Y'),
+        (i64::from(months), 'M'),
+        (i64::from(days), 'D'),
+    ] {
+        if value != 0 {
+            let _ = write!(out, "{value}{unit}");
+        }
+    }
+    if micros != 0 {
+        out.push('T');
+        let hours = micros / 3_600_000_000;
+        let minutes = micros % 3_600_000_000 / 60_000_000;
+        let second_micros = micros % 60_000_000;
+        if hours != 0 {
+            let _ = write!(out, "{hours}H");
+        }
+        if minutes != 0 {
+            let _ = write!(out, "{minutes}M");
+        }
+        if second_micros != 0 {
+            let sign = if second_micros < 0 { "-" } else { "" };
+            let seconds = (second_micros / 1_000_000).abs();
+            let fraction = (second_micros % 1_000_000).abs();
+            if fraction == 0 {
+                let _ = write!(out, "{sign}{seconds}S");
+            } else {
+                let fraction = format!("{fraction:06}");
+                let _ = write!(out, "{sign}{seconds}.{}S", fraction.trim_end_matches('0'));
+            }
+        }
+    }
+    if out == "P" {
+        out.push_str("T0S");
+    }
+    out
+}
+
+/// `INET`/`CIDR` in Postgres' text form: the prefix length is shown for networks and
+/// for host addresses with a netmask.
+fn inet_to_string(bytes: &[u8], is_cidr: bool) -> Result<String, BoxDynError> {
+    let mut reader = Reader::new(bytes);
+    let header = reader.take(4)?;
+    let (family, bits, len) = (header[0], header[1], usize::from(header[3]));
+    let address = reader.take(len)?;
+    let (address, max_bits) = match family {
+        2 => (std::net::IpAddr::from(<[u8; 4]>::try_from(address)?), 32),
+        3 => (std::net::IpAddr::from(<[u8; 16]>::try_from(address)?), 128),
+        other => return Err(format!("invalid inet address family {}", other).into()),
+    };
+    Ok(if is_cidr || bits != max_bits {
+        format!("{}/{}", address, bits)
+    } else {
+        address.to_string()
+    })
+}
+
+fn mac_to_string(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect::<Vec<_>>()
+        .join(":")
+}
+
+/// `MONEY` is stored in the currency's smallest unit; this assumes the two fractional
+/// digits of the default `lc_monetary` locales.
+fn money_to_string(cents: i64) -> String {
+    let sign = if cents < 0 { "-" } else { "" };
+    let cents = i128::from(cents).abs();
+    format!("{}{}.{:02}", sign, cents / 100, cents % 100)
+}
+
+/// Renders a binary `NUMERIC` as exact decimal text with its display scale,
+/// e.g. `-123.4500`, `NaN` or `Infinity`.
+pub fn numeric_to_string(bytes: &[u8]) -> Result<String, BoxDynError> {
+    let mut reader = Reader::new(bytes);
+    let ndigits = reader.i16()?;
+    let weight = i32::from(reader.i16()?);
+    let sign = reader.u16()?;
+    let dscale = usize::from(reader.u16()?);
+    match sign {
+        NUMERIC_NAN => return Ok("NaN".to_string()),
+        NUMERIC_PINF => return Ok("Infinity".to_string()),
+        NUMERIC_NINF => return Ok("-Infinity".to_string()),
+        NUMERIC_POS | NUMERIC_NEG => {}
+        other => return Err(format!("invalid numeric sign 0x{:04x}", other).into()),
+    }
+    let digits = (0..ndigits)
+        .map(|_| reader.i16())
+        .collect::<Result<Vec<i16>, _>>()?;
+    // Base-10000 digit at position `i`, where digit 0 is multiplied by 10000^weight
+    let digit = |i: i32| -> i16 {
+        usize::try_from(i)
+            .ok()
+            .and_then(|i| digits.get(i).copied())
+            .unwrap_or(0)
+    };
+
+    let mut text = String::new();
+    if sign == NUMERIC_NEG && !digits.iter().all(|&d| d == 0) {
+        text.push('-');
+    }
+    if weight < 0 {
+        text.push('0');
+    } else {
+        for i in 0..=weight {
+            if i == 0 {
+                write!(text, "{}", digit(i))?;
+            } else {
+                write!(text, "{:04}", digit(i))?;
+            }
+        }
+    }
+    if dscale > 0 {
+        let mut fraction = String::with_capacity(dscale + 4);
+        let mut i = weight + 1;
+        while fraction.len() < dscale {
+            write!(fraction, "{:04}", digit(i))?;
+            i += 1;
+        }
+        fraction.truncate(dscale);
+        text.push('.');
+        text.push_str(&fraction);
+    }
+    Ok(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn numeric(weight: i16, sign: u16, dscale: u16, digits: &[i16]) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(&(digits.len() as i16).to_be_bytes());
+        bytes.extend_from_slice(&weight.to_be_bytes());
+        bytes.extend_from_slice(&sign.to_be_bytes());
+        bytes.extend_from_slice(&dscale.to_be_bytes());
+        for d in digits {
+            bytes.extend_from_slice(&d.to_be_bytes());
+        }
+        bytes
+    }
+
+    #[test]
+    fn test_numeric_to_string() {
+        let cases: &[(Vec<u8>, &str)] = &[
+            (numeric(0, NUMERIC_POS, 2, &[123, 4500]), "123.45"),
+            (numeric(0, NUMERIC_NEG, 4, &[123, 4500]), "-123.4500"),
+            (numeric(-1, NUMERIC_POS, 3, &[10]), "0.001"),
+            (numeric(-2, NUMERIC_POS, 8, &[1234]), "0.00001234"),
+            (numeric(2, NUMERIC_POS, 0, &[1, 0]), "100000000"),
+            (numeric(0, NUMERIC_POS, 0, &[]), "0"),
+            (numeric(0, NUMERIC_NAN, 0, &[]), "NaN"),
+            // 2^256 - 1, as stored in numeric(78,0) balance columns
+            (
+                numeric(
+                    19,
+                    NUMERIC_POS,
+                    0,
+                    &[
+                        11, 5792, 892, 3731, 6195, 4235, 7098, 5008, 6879, 785, 3269, 9846, 6564,
+                        564, 394, 5758, 4007, 9131, 2963, 9935,
+                    ],
+                ),
+                "115792089237316195423570985008687907853269984665640564039457584007913129639935",
+            ),
+        ];
+        for (bytes, expected) in cases {
+            assert_eq!(numeric_to_string(bytes).unwrap(), *expected);
+        }
+    }
+
+    fn element(bytes: &[u8]) -> Vec<u8> {
+        let mut out = (bytes.len() as i32).to_be_bytes().to_vec();
+        out.extend_from_slice(bytes);
+        out
+    }
+
+    fn array(element_oid: u32, dims: &[i32], elements: &[Option<&[u8]>]) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(&(dims.len() as i32).to_be_bytes());
+        bytes.extend_from_slice(&i32::from(elements.contains(&None)).to_be_bytes());
+        bytes.extend_from_slice(&element_oid.to_be_bytes());
+        for dim in dims {
+            bytes.extend_from_slice(&dim.to_be_bytes());
+            bytes.extend_from_slice(&1i32.to_be_bytes());
+        }
+        for e in elements {
+            match e {
+                Some(e) => bytes.extend(element(e)),
+                None => bytes.extend_from_slice(&(-1i32).to_be_bytes()),
+            }
+        }
+        bytes
+    }
+
+    #[test]
+    fn test_one_dimensional_arrays() {
+        let options = DecodeOptions::default();
+        let topics = array(oid::TEXT, &[3], &[Some(b"0xddf2"), None, Some(b"0x0000")]);
+        assert_eq!(
+            array_to_json(&topics, &options).unwrap(),
+            serde_json::json!(["0xddf2", null, "0x0000"])
+        );
+        let trace_address = array(
+            oid::INT8,
+            &[2],
+            &[Some(&0i64.to_be_bytes()), Some(&3i64.to_be_bytes())],
+        );
+        assert_eq!(
+            array_to_json(&trace_address, &options).unwrap(),
+            serde_json::json!([0, 3])
+        );
+        assert_eq!(
+            array_to_json(&array(oid::INT4, &[], &[]), &options).unwrap(),
+            serde_json::json!([])
+        );
+    }
+
+    #[test]
+    fn test_multi_dimensional_array() {
+        let cells: Vec<[u8; 4]> = (1..=6i32).map(i32::to_be_bytes).collect();
+        let elements: Vec<Option<&[u8]>> = cells.iter().map(|c| Some(&c[..])).collect();
+        let matrix = array(oid::INT4, &[2, 3], &elements);
+        assert_eq!(
+            array_to_json(&matrix, &DecodeOptions::default()).unwrap(),
+            serde_json::json!([[1, 2, 3], [4, 5, 6]])
+        );
+    }
+
+    #[test]
+    fn test_record() {
+        let mut record = 3i32.to_be_bytes().to_vec();
+        record.extend_from_slice(&oid::INT4.to_be_bytes());
+        record.extend(element(&7i32.to_be_bytes()));
+        record.extend_from_slice(&oid::TEXT.to_be_bytes());
+        record.extend_from_slice(&(-1i32).to_be_bytes());
+        record.extend_from_slice(&1007u32.to_be_bytes());
+        record.extend(element(&array(
+            oid::INT4,
+            &[1],
+            &[Some(&1i32.to_be_bytes())],
+        )));
+
+        let options = DecodeOptions::default();
+        assert_eq!(
+            record_to_json(&record, None, &options).unwrap(),
+            serde_json::json!({"f1": 7, "f2": null, "f3": [1]})
+        );
+        assert_eq!(
+            record_to_json(&record, Some(&["id", "label", "path"]), &options).unwrap(),
+            serde_json::json!({"id": 7, "label": null, "path": [1]})
+        );
+    }
+
+    #[test]
+    fn test_interval() {
+        assert_eq!(
+            interval_to_json(14, 3, 0),
+            serde_json::json!({"iso": "P1Y2M3D", "months": 14, "days": 3, "microseconds": 0})
+        );
+        assert_eq!(iso_duration(0, 1, 43_200_000_000), "P1DT12H");
+        assert_eq!(iso_duration(0, 0, 3_723_500_000), "PT1H2M3.5S");
+        assert_eq!(iso_duration(-1, 0, -1_500_000), "P-1MT-1.5S");
+        assert_eq!(iso_duration(0, 0, 0), "PT0S");
+    }
+
+    #[test]
+    fn test_network_and_money_types() {
+        let options = DecodeOptions::default();
+        let host = [2, 32, 0, 4, 192, 168, 0, 1];
+        assert_eq!(to_json(oid::INET, &host, &options).unwrap(), "192.168.0.1");
+        let network = [2, 24, 1, 4, 10, 0, 0, 0];
+        assert_eq!(
+            to_json(oid::CIDR, &network, &options).unwrap(),
+            "10.0.0.0/24"
+        );
+        let mut v6 = vec![3, 64, 0, 16, 0x20, 0x01, 0x0d, 0xb8];
+        v6.extend_from_slice(&[0; 12]);
+        assert_eq!(to_json(oid::INET, &v6, &options).unwrap(), "2001:db8::/64");
+        let mac = [0x08, 0x00, 0x2b, 0x01, 0x02, 0x03];
+        assert_eq!(
+            to_json(oid::MACADDR, &mac, &options).unwrap(),
+            "08:00:2b:01:02:03"
+        );
+        let money = (-123_456i64).to_be_bytes();
+        assert_eq!(to_json(oid::MONEY, &money, &options).unwrap(), "-1234.56");
+        let unknown = [0xff, 0x00];
+        assert_eq!(to_json(1560, &unknown, &options).unwrap(), "0xff00");
+    }
+
+    #[test]
+    fn test_numeric_truncated_input() {
+        assert!(numeric_to_string(&[0, 1, 0]).is_err());
+    }
+}
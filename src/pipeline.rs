@@ -0,0 +1,402 @@
+use regex::Regex;
+use serde::Serialize;
+use sqlparser::ast::{Expr, Ident, SelectItem, SetExpr, Statement, TableFactor};
+use sqlparser::dialect::PostgreSqlDialect;
+use sqlparser::parser::Parser;
+use utoipa::ToSchema;
+
+use crate::chains::ChainRegistry;
+use crate::utils;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Engine {
+    Sui,
+    Eql,
+    Indexed,
+}
+
+impl Engine {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Engine::Sui => "sui",
+            Engine::Eql => "eql",
+            Engine::Indexed => "indexed",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Stage {
+    Type,
+    Blacklist,
+    Chains,
+    Parse,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct PipelineError {
+    pub stage: Stage,
+    pub message: String,
+    /// 1-based position in the normalized query, when known.
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ResolvedTable {
+    /// The reference as written, e.g. `arbitrum.logs`.
+    pub reference: String,
+    /// User-facing canonical name, e.g. `arb.logs`.
+    pub table: String,
+    /// Physical table the query runs against, e.g. `arb_logs`.
+    pub physical: String,
+}
+
+/// Everything the server decides about a query before running it.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct Validation {
+    pub valid: bool,
+    pub normalized_query: String,
+    pub engine: Option<Engine>,
+    /// Flattened SQL sent to the database, indexed queries only.
+    pub sql: Option<String>,
+    pub tables: Vec<ResolvedTable>,
+    pub warnings: Vec<String>,
+    pub errors: Vec<PipelineError>,
+}
+
+/// Runs the pre-execution pipeline: comment stripping, blacklist, engine selection,
+/// chain table flattening and the gluesql parse. Nothing is executed.
+pub fn validate(input: &str, type_param: &str, chains: &ChainRegistry) -> Validation {
+    let normalized_query = utils::remove_sql_comments(input);
+    let mut validation = Validation {
+        valid: false,
+        normalized_query,
+        engine: None,
+        sql: None,
+        tables: Vec::new(),
+        warnings: Vec::new(),
+        errors: Vec::new(),
+    };
+    let query = validation.normalized_query.clone();
+
+    if query != input {
+        validation
+            .warnings
+            .push("Comments were stripped from the query".to_string());
+    }
+
+    if !matches!(type_param, "rpc" | "indexed") {
+        validation.errors.push(PipelineError {
+            stage: Stage::Type,
+            message: "Invalid type. Supported values are: 'rpc' or 'indexed'.".to_string(),
+            line: None,
+            column: None,
+        });
+        return validation;
+    }
+
+    if let Some((offset, _keyword)) = utils::find_blacklisted(&query) {
+        let (line, column) = line_column(&query, offset);
+        validation.errors.push(PipelineError {
+            stage: Stage::Blacklist,
+            message: "Only SELECT queries are allowed. CREATE, DROP, INSERT, UPDATE, DELETE, and other write ops are blocked.".to_string(),
+            line: Some(line),
+            column: Some(column),
+        });
+        return validation;
+    }
+
+    let referenced = chains.referenced_chains(&query);
+    let unavailable: Vec<&str> = referenced
+        .iter()
+        .filter(|chain| if type_param == "rpc" { !chain.rpc() } else { !chain.indexed() })
+        .map(|chain| chain.id.as_str())
+        .collect();
+    if !unavailable.is_empty() {
+        validation.errors.push(PipelineError {
+            stage: Stage::Chains,
+            message: format!(
+                "Chains not available for '{}' queries: {}",
+                type_param,
+                unavailable.join(", ")
+            ),
+            line: None,
+            column: None,
+        });
+        return validation;
+    }
+
+    if type_param == "rpc" {
+        validation.engine = Some(if utils::is_sui_rpc_query(&query, chains) {
+            Engine::Sui
+        } else {
+            Engine::Eql
+        });
+        if referenced.is_empty() {
+            validation
+                .warnings
+                .push("No known chain is referenced; the query will run as EQL".to_string());
+        }
+        validation.valid = true;
+        return validation;
+    }
+
+    validation.engine = Some(Engine::Indexed);
+    for (reference, physical) in utils::resolve_chain_tables(&query, chains) {
+        let table = match chains.split_physical(&physical) {
+            Some((chain, table)) => format!("{}.{}", chain.id, table),
+            None => physical.clone(),
+        };
+        if reference != table {
+            validation
+                .warnings
+                .push(format!("'{}' was resolved to '{}'", reference, table));
+        }
+        validation.tables.push(ResolvedTable {
+            reference,
+            table,
+            physical,
+        });
+    }
+    if validation.tables.is_empty() {
+        validation
+            .warnings
+            .push("The query does not reference any chain table".to_string());
+    }
+
+    let flattened_query = utils::flatten_known_chain_tables(&query, chains);
+    if let Err(e) = gluesql::prelude::parse(&flattened_query) {
+        let message = e.to_string();
+        let (line, column) = parse_error_location(&message).unzip();
+        validation.errors.push(PipelineError {
+            stage: Stage::Parse,
+            message,
+            line,
+            column,
+        });
+    }
+    validation.sql = Some(flattened_query);
+    validation.valid = validation.errors.is_empty();
+    validation
+}
+
+/// One item of a select list, as far as the type of its result column is concerned.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SelectColumn {
+    /// A bare column reference such as `fee`, `t.fee` or `t.fee AS f`.
+    Column { name: String, column: String },
+    /// `*` or `t.*`: table columns under their own names.
+    Wildcard,
+    /// Any other expression; its type is whatever the database computes.
+    Computed { name: String },
+}
+
+/// Parses the select list of flattened SQL. Returns `None` unless the query is a single
+/// `SELECT` reading only from tables, since output columns of set operations, CTEs and
+/// subqueries cannot be traced back to table columns.
+pub fn select_columns(sql: &str) -> Option<Vec<SelectColumn>> {
+    let mut statements = Parser::parse_sql(&PostgreSqlDialect {}, sql).ok()?;
+    if statements.len() != 1 {
+        return None;
+    }
+    let Statement::Query(query) = statements.remove(0) else {
+        return None;
+    };
+    if query.with.is_some() {
+        return None;
+    }
+    let SetExpr::Select(select) = *query.body else {
+        return None;
+    };
+    let is_table = |relation: &TableFactor| matches!(relation, TableFactor::Table { .. });
+    let tables_only = select.from.iter().all(|from| {
+        is_table(&from.relation) && from.joins.iter().all(|join| is_table(&join.relation))
+    });
+    if select.from.is_empty() || !tables_only {
+        return None;
+    }
+    let columns = select
+        .projection
+        .into_iter()
+        .map(|item| match item {
+            SelectItem::UnnamedExpr(expr) => select_column(output_name(&expr), &expr),
+            SelectItem::ExprWithAlias { expr, alias } => select_column(identifier(&alias), &expr),
+            SelectItem::Wildcard(_) | SelectItem::QualifiedWildcard(..) => SelectColumn::Wildcard,
+        })
+        .collect();
+    Some(columns)
+}
+
+fn select_column(name: String, expr: &Expr) -> SelectColumn {
+    match column_reference(expr) {
+        Some(column) => SelectColumn::Column { name, column },
+        None => SelectColumn::Computed { name },
+    }
+}
+
+fn column_reference(expr: &Expr) -> Option<String> {
+    match expr {
+        Expr::Identifier(ident) => Some(identifier(ident)),
+        Expr::CompoundIdentifier(parts) => parts.last().map(identifier),
+        _ => None,
+    }
+}
+
+/// The name Postgres gives an unaliased select item.
+fn output_name(expr: &Expr) -> String {
+    if let Some(column) = column_reference(expr) {
+        return column;
+    }
+    match expr {
+        Expr::Function(function) => function.name.0.last().map(identifier),
+        Expr::Cast { expr, .. } | Expr::Nested(expr) => Some(output_name(expr)),
+        _ => None,
+    }
+    .unwrap_or_else(|| "?column?".to_string())
+}
+
+/// Unquoted identifiers are folded to lower case, as Postgres does.
+fn identifier(ident: &Ident) -> String {
+    match ident.quote_style {
+        Some(_) => ident.value.clone(),
+        None => ident.value.to_lowercase(),
+    }
+}
+
+/// Converts a byte offset into a 1-based (line, column) pair.
+fn line_column(text: &str, offset: usize) -> (usize, usize) {
+    let before = &text[..offset];
+    let line = before.matches('\n').count() + 1;
+    let column = before
+        .rsplit('\n')
+        .next()
+        .map(|l| l.chars().count())
+        .unwrap_or(0)
+        + 1;
+    (line, column)
+}
+
+/// sqlparser reports positions as `Line: 1, Column 8`; gluesql keeps that text in its errors.
+fn parse_error_location(message: &str) -> Option<(usize, usize)> {
+    let re = Regex::new(r"Line: (\d+), Column:? (\d+)").unwrap();
+    let caps = re.captures(message)?;
+    Some((caps[1].parse().ok()?, caps[2].parse().ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_indexed_query() {
+        let chains = ChainRegistry::builtin();
+        let validation = validate(
+            "SELECT number FROM arbitrum.blocks -- latest\nLIMIT 1",
+            "indexed",
+            &chains,
+        );
+        assert!(validation.valid, "{:?}", validation.errors);
+        assert_eq!(validation.engine, Some(Engine::Indexed));
+        assert_eq!(validation.tables[0].table, "arb.blocks");
+        assert_eq!(validation.tables[0].physical, "arb_blocks");
+        assert_eq!(
+            validation.sql.as_deref(),
+            Some("SELECT number FROM arb_blocks \nLIMIT 1")
+        );
+    }
+
+    #[test]
+    fn test_validate_resolves_chain_ids_with_underscores() {
+        let chains = ChainRegistry::from_json(
+            r#"{"chains": [
+                {"id": "op_sepolia", "aliases": ["opsep"], "family": "evm", "chain_id": 11155420, "native_currency": "ETH", "decimals": 18, "availability": "indexed"}
+            ]}"#,
+        )
+        .unwrap();
+        let validation = validate("SELECT * FROM opsep.logs", "indexed", &chains);
+        assert_eq!(validation.tables[0].table, "op_sepolia.logs");
+        assert_eq!(validation.tables[0].physical, "op_sepolia_logs");
+    }
+
+    #[test]
+    fn test_validate_reports_blacklist_location() {
+        let chains = ChainRegistry::builtin();
+        let validation = validate("SELECT 1\nFROM eth.blocks; DROP TABLE x", "indexed", &chains);
+        assert!(!validation.valid);
+        let error = &validation.errors[0];
+        assert_eq!(error.stage, Stage::Blacklist);
+        assert_eq!((error.line, error.column), (Some(2), Some(18)));
+    }
+
+    #[test]
+    fn test_validate_rejects_blacklisted_queries() {
+        let chains = ChainRegistry::builtin();
+        for query in [
+            "INSERT INTO eth.blocks (number) VALUES (1)",
+            "UPDATE eth.blocks SET number = 1",
+            "SELECT pg_sleep(10) FROM eth.blocks",
+            "SELECT abi FROM sandworm_internal.sandworm_abis",
+        ] {
+            let validation = validate(query, "indexed", &chains);
+            assert!(!validation.valid, "{query}");
+            assert_eq!(validation.errors[0].stage, Stage::Blacklist, "{query}");
+        }
+    }
+
+    #[test]
+    fn test_validate_selects_rpc_engine() {
+        let chains = ChainRegistry::builtin();
+        let validation = validate("GET balance FROM account 0x0 ON eth", "rpc", &chains);
+        assert_eq!(validation.engine, Some(Engine::Eql));
+        let validation = validate("GET * FROM object 0x5 ON sui", "rpc", &chains);
+        assert_eq!(validation.engine, Some(Engine::Sui));
+    }
+
+    #[test]
+    fn test_parse_error_location() {
+        assert_eq!(
+            parse_error_location("Expected end of statement, found: x at Line: 3, Column 7"),
+            Some((3, 7))
+        );
+        assert_eq!(parse_error_location("unexpected token"), None);
+    }
+
+    #[test]
+    fn test_select_columns() {
+        let column = |name: &str, column: &str| SelectColumn::Column {
+            name: name.to_string(),
+            column: column.to_string(),
+        };
+        let computed = |name: &str| SelectColumn::Computed {
+            name: name.to_string(),
+        };
+        assert_eq!(
+            select_columns(
+                "SELECT Fee, t.fee AS f, SUM(fee) AS fee, fee::TEXT, value + 1, t.* \
+                 FROM eth_transactions t JOIN eth_blocks b ON b.number = t.block_number \
+                 GROUP BY 1"
+            ),
+            Some(vec![
+                column("fee", "fee"),
+                column("f", "fee"),
+                computed("fee"),
+                computed("fee"),
+                computed("?column?"),
+                SelectColumn::Wildcard,
+            ])
+        );
+        assert_eq!(
+            select_columns("SELECT fee FROM (SELECT fee FROM eth_transactions) t"),
+            None
+        );
+        assert_eq!(
+            select_columns("SELECT fee FROM a UNION SELECT fee FROM b"),
+            None
+        );
+        assert_eq!(select_columns("WITH t AS (SELECT 1) SELECT * FROM t"), None);
+        assert_eq!(select_columns("SELECT 1"), None);
+    }
+}
@@ -0,0 +1,966 @@
+use std::collections::HashMap;
+use std::io::Cursor;
+
+use chrono_tz::Tz;
+use parquet::basic::Compression;
+use rocket::http::{Accept, ContentType};
+use rocket::request::{FromRequest, Outcome};
+use rocket::response::{self, Responder, Response};
+use rocket::{FromForm, FromFormField, Request};
+use serde::ser::SerializeStruct;
+use serde::{Serialize, Serializer};
+use serde_json::{json, Value};
+use sqlx::any::{Any, AnyRow};
+use sqlx::{Column as _, Describe, Row, TypeInfo};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::arrow_export::{self, ParquetOptions, ARROW_STREAM_MEDIA_TYPE};
+use crate::encoding::{self, AppendedColumn, IndexedRows, Layout};
+use crate::export::{self, DelimitedOptions};
+use crate::pipeline::Engine;
+use crate::sql_to_json::{
+    self, row_to_json_with, BigIntFormat, BinaryFormat, DecimalFormat, DecodeOptions,
+    DecodeWarning, Decoder, DuplicatePolicy, TimeFormat,
+};
+use crate::tabular;
+
+/// Bumped whenever the envelope shape changes in a way clients must notice.
+pub const ENVELOPE_VERSION: u32 = 1;
+
+const DEFAULT_ROW_LIMIT: usize = 10_000;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, FromFormField, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    /// Indexed rows as objects, interpreter results as one `result` row per statement.
+    #[default]
+    Json,
+    /// Every engine flattened into typed columns and one object per row.
+    Table,
+    /// Column metadata once, then positional arrays laid out according to `orient`.
+    Columnar,
+    /// Comma-separated values with a header row.
+    Csv,
+    /// Tab-separated values with a header row.
+    Tsv,
+    /// Arrow IPC stream with natively typed columns.
+    Arrow,
+    /// Parquet file with the same column types as `arrow`.
+    Parquet,
+}
+
+impl OutputFormat {
+    /// Formats whose rows are positional arrays rather than objects.
+    pub fn positional(self) -> bool {
+        matches!(
+            self,
+            OutputFormat::Columnar
+                | OutputFormat::Csv
+                | OutputFormat::Tsv
+                | OutputFormat::Arrow
+                | OutputFormat::Parquet
+        )
+    }
+
+    /// Formats encoded from an Arrow record batch rather than from the envelope's rows.
+    pub fn arrow_backed(self) -> bool {
+        matches!(self, OutputFormat::Arrow | OutputFormat::Parquet)
+    }
+
+    /// Format implied by the request's `Accept` header, if it names one we produce.
+    pub fn from_accept(accept: &Accept) -> Option<Self> {
+        let preferred = accept.preferred().media_type();
+        match (preferred.top().as_str(), preferred.sub().as_str()) {
+            ("text", "csv") => Some(OutputFormat::Csv),
+            ("text", "tab-separated-values") => Some(OutputFormat::Tsv),
+            ARROW_STREAM_MEDIA_TYPE => Some(OutputFormat::Arrow),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, FromFormField, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Orient {
+    /// `rows` holds one array per row.
+    #[default]
+    Rows,
+    /// `rows` holds one array per column.
+    Columns,
+}
+
+/// Extra decoding applied to indexed results.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, FromFormField, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum DecodeMode {
+    /// Adds `event_name`, `function_name` and `args` columns decoded with the contract
+    /// ABIs registered through `POST /v1/abis`.
+    Abi,
+}
+
+/// Extra columns looked up for indexed and EQL results.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, FromFormField, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Enrichment {
+    /// Adds `function_signatures` for calldata selectors and `event_signatures` for log
+    /// `topic0` hashes, matched against the local signature database.
+    Signatures,
+}
+
+/// Wire encoding for the JSON-shaped formats (`json`, `table`, `columnar`).
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, FromFormField, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Encoding {
+    #[default]
+    Json,
+    /// MessagePack, with binary columns as `bin` values.
+    Msgpack,
+    /// CBOR, with binary columns as byte strings.
+    Cbor,
+}
+
+impl Encoding {
+    /// Encoding implied by the request's `Accept` header, if it names one we produce.
+    pub fn from_accept(accept: &Accept) -> Option<Self> {
+        let preferred = accept.preferred().media_type();
+        match (preferred.top().as_str(), preferred.sub().as_str()) {
+            ("application", "json") => Some(Encoding::Json),
+            ("application", "msgpack" | "x-msgpack" | "vnd.msgpack") => Some(Encoding::Msgpack),
+            ("application", "cbor") => Some(Encoding::Cbor),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, FromFormField, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ParquetCompression {
+    None,
+    #[default]
+    Snappy,
+    Gzip,
+    Zstd,
+    Lz4,
+}
+
+/// Options shared by every engine, passed as query parameters next to `query`.
+#[derive(Debug, Default, FromForm, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct QueryOptions {
+    #[param(inline)]
+    pub format: Option<OutputFormat>,
+    /// Wire encoding for `format=json|table|columnar`; taken from `Accept` when omitted.
+    #[param(inline)]
+    pub encoding: Option<Encoding>,
+    /// Array layout for `format=columnar`.
+    #[param(inline)]
+    pub orient: Option<Orient>,
+    /// Maximum number of rows to return, at most the server's `MAX_RESULT_ROWS`;
+    /// `truncated` is set when more were available.
+    pub limit: Option<usize>,
+    /// Include the engine-specific payload under `raw`.
+    pub raw: Option<bool>,
+    /// Single-character field delimiter for `format=csv|tsv`.
+    pub delimiter: Option<String>,
+    /// Text written for null cells in `format=csv|tsv`; empty by default.
+    pub null: Option<String>,
+    /// Sends the result as an attachment with this file name.
+    pub filename: Option<String>,
+    /// How `NUMERIC`/`DECIMAL` values are written: exact strings by default, or numbers.
+    /// JSON rows keep every digit of a number; other outputs write numbers that no
+    /// `f64` holds exactly as strings.
+    #[param(inline)]
+    pub decimal: Option<DecimalFormat>,
+    /// How 64-bit integers are written: numbers by default, strings, or `auto` to
+    /// stringify only values JavaScript cannot hold exactly.
+    #[param(inline)]
+    pub bigint: Option<BigIntFormat>,
+    /// How binary columns are written in JSON and text formats: `0x` hex by default, or base64.
+    #[param(inline)]
+    pub binary: Option<BinaryFormat>,
+    /// How indexed columns sharing a name are returned: merged into arrays by default,
+    /// suffixed (`id`, `id_2`) or rejected.
+    #[param(inline)]
+    pub duplicates: Option<DuplicatePolicy>,
+    /// Column compression for `format=parquet`; snappy by default.
+    #[param(inline)]
+    pub compression: Option<ParquetCompression>,
+    /// Maximum rows per row group for `format=parquet`.
+    pub row_group_size: Option<usize>,
+    /// How dates, times and timestamps are written, including the `timestamp` and
+    /// `timestamp_ms` fields of normalized interpreter results.
+    #[param(inline)]
+    pub time_format: Option<TimeFormat>,
+    /// IANA zone, e.g. `Europe/Paris`, that timestamps are rendered in with
+    /// `time_format=rfc3339`. Dates and times of day are never converted. Interpreter
+    /// results carry no field types, so only the normalized `timestamp` (epoch seconds)
+    /// and `timestamp_ms` (epoch milliseconds) columns of `format=table` and the other
+    /// tabular formats are treated as timestamps. A warning says when `tz` is not applied.
+    pub tz: Option<String>,
+    /// Fail the request when a value cannot be decoded instead of returning it as `null`.
+    pub strict: Option<bool>,
+    /// `abi` decodes EVM log and calldata columns with registered contract ABIs;
+    /// indexed queries only.
+    #[param(inline)]
+    pub decode: Option<DecodeMode>,
+    /// `signatures` names calldata selectors and event topics from the local signature
+    /// database; indexed and EQL queries only.
+    #[param(inline)]
+    pub enrich: Option<Enrichment>,
+}
+
+impl QueryOptions {
+    /// The requested `limit`, capped at `MAX_RESULT_ROWS` so clients cannot lift the
+    /// server's result size cap.
+    pub fn row_limit(&self) -> usize {
+        let max_rows = std::env::var("MAX_RESULT_ROWS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_ROW_LIMIT);
+        self.limit.map_or(max_rows, |limit| limit.min(max_rows))
+    }
+
+    pub fn include_raw(&self) -> bool {
+        self.raw.unwrap_or(false)
+    }
+
+    pub fn is_strict(&self) -> bool {
+        self.strict.unwrap_or(false)
+    }
+
+    pub fn decodes_abi(&self) -> bool {
+        self.decode == Some(DecodeMode::Abi)
+    }
+
+    pub fn enriches_signatures(&self) -> bool {
+        self.enrich == Some(Enrichment::Signatures)
+    }
+
+    pub fn output_format(&self) -> OutputFormat {
+        self.format.unwrap_or_default()
+    }
+
+    /// Resolves `format` and `encoding`, falling back to the `Accept` header and then JSON.
+    pub fn negotiate(&mut self, accept: Option<&Accept>) {
+        if self.format.is_none() {
+            self.format = accept.and_then(OutputFormat::from_accept);
+        }
+        if self.encoding.is_none() {
+            self.encoding = accept.and_then(Encoding::from_accept);
+        }
+    }
+
+    /// Encoding of the envelope, or `None` for formats that are not envelope-shaped.
+    pub fn envelope_encoding(&self) -> Option<Encoding> {
+        match self.output_format() {
+            OutputFormat::Json | OutputFormat::Table | OutputFormat::Columnar => {
+                Some(self.encoding.unwrap_or_default())
+            }
+            _ => None,
+        }
+    }
+
+    fn layout(&self) -> Layout {
+        match (self.output_format(), self.orient()) {
+            (OutputFormat::Columnar, Orient::Columns) => Layout::ColumnArrays,
+            (format, _) if format.positional() => Layout::Arrays,
+            _ => Layout::Objects,
+        }
+    }
+
+    pub fn delimited(&self) -> Result<DelimitedOptions, String> {
+        let default = if self.output_format() == OutputFormat::Tsv { b'\t' } else { b',' };
+        let delimiter = match self.delimiter.as_deref() {
+            None => default,
+            Some(d) if d.len() == 1 && d.is_ascii() => d.as_bytes()[0],
+            Some("\\t") => b'\t',
+            Some(d) => return Err(format!("Invalid delimiter '{}': expected a single ASCII character", d)),
+        };
+        Ok(DelimitedOptions {
+            delimiter,
+            null: self.null.clone().unwrap_or_default(),
+        })
+    }
+
+    /// `filename` reduced to characters that are safe inside a `Content-Disposition` header.
+    pub fn attachment_name(&self) -> Option<String> {
+        let name: String = self
+            .filename
+            .as_deref()?
+            .chars()
+            .filter(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_'))
+            .collect();
+        (!name.is_empty()).then_some(name)
+    }
+
+    pub fn parquet(&self) -> Result<ParquetOptions, String> {
+        let row_group_size = match self.row_group_size {
+            None => ParquetOptions::DEFAULT_ROW_GROUP_SIZE,
+            Some(0) => return Err("Invalid row_group_size: must be at least 1".to_string()),
+            Some(size) => size,
+        };
+        let compression = match self.compression.unwrap_or_default() {
+            ParquetCompression::None => Compression::UNCOMPRESSED,
+            ParquetCompression::Snappy => Compression::SNAPPY,
+            ParquetCompression::Gzip => Compression::GZIP(Default::default()),
+            ParquetCompression::Zstd => Compression::ZSTD(Default::default()),
+            ParquetCompression::Lz4 => Compression::LZ4_RAW,
+        };
+        Ok(ParquetOptions {
+            compression,
+            row_group_size,
+        })
+    }
+
+    pub fn time_zone(&self) -> Result<Option<Tz>, String> {
+        self.tz
+            .as_deref()
+            .map(|tz| {
+                tz.parse::<Tz>()
+                    .map_err(|_| format!("Invalid tz '{}': expected an IANA time zone", tz))
+            })
+            .transpose()
+    }
+
+    /// Decoding choices; an invalid `tz` is ignored here and rejected by [`Self::time_zone`].
+    pub fn decode_options(&self) -> DecodeOptions {
+        DecodeOptions {
+            decimal: self.decimal.unwrap_or_default(),
+            bigint: self.bigint.unwrap_or_default(),
+            binary: self.binary.unwrap_or_default(),
+            duplicates: self.duplicates.unwrap_or_default(),
+            time_format: self.time_format.unwrap_or_default(),
+            tz: self.time_zone().ok().flatten(),
+        }
+    }
+
+    pub fn orient(&self) -> Orient {
+        self.orient.unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct Column {
+    pub name: String,
+    /// Backend type name for indexed results. Interpreter results are `json`, except with
+    /// `format=table`, where each column gets the JSON type of its values: `int64`,
+    /// `uint64`, `float`, `bool`, `text`, `json`, or `null` when every value is null.
+    #[serde(rename = "type")]
+    pub type_name: String,
+    /// Backend-independent type: `int64`, `uint64`, `decimal`, `float`, `bool`, `text`,
+    /// `bytes`, `date`, `time`, `timestamp`, `timestamptz`, `json`, `unknown` or
+    /// `array<...>`. Postgres types without a decoder are `bytes`.
+    /// Postgres `numeric` columns read straight from a table are `decimal(p,s)` when the
+    /// table declares a precision; otherwise decimals are plain `decimal`.
+    pub logical_type: String,
+    /// Whether the column may hold nulls; omitted when the backend does not say.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nullable: Option<bool>,
+    /// Set when the backend type has no dedicated decoder: Postgres values are returned
+    /// as their encoded bytes, other backends' as text.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub unsupported: bool,
+}
+
+/// What a query returned before the response options shape it, so that a stored job
+/// result can be encoded again in any format.
+pub enum QueryOutput {
+    /// Sui or EQL interpreter results, serialized.
+    Interpreter(Vec<Value>),
+    /// Indexed rows cut to the limit, and the statement as the driver describes it.
+    Indexed {
+        rows: Vec<AnyRow>,
+        described: Option<Describe<Any>>,
+        truncated: bool,
+    },
+}
+
+/// Response shape shared by the Sui, EQL and indexed engines.
+#[derive(Debug, ToSchema)]
+pub struct Envelope {
+    pub version: u32,
+    pub engine: Engine,
+    pub columns: Vec<Column>,
+    #[schema(value_type = Vec<Object>)]
+    pub rows: Vec<Value>,
+    pub row_count: usize,
+    pub truncated: bool,
+    pub elapsed_ms: u64,
+    pub warnings: Vec<String>,
+    /// Values that could not be decoded and were returned as `null`.
+    pub decode_warnings: Vec<DecodeWarning>,
+    pub request_id: String,
+    /// Set for `format=columnar` to say how `rows` is laid out.
+    pub orient: Option<Orient>,
+    #[schema(value_type = Option<Object>)]
+    pub raw: Option<Value>,
+}
+
+impl Serialize for Envelope {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.serialize_fields(
+            &self.rows,
+            &self.decode_warnings,
+            self.raw.as_ref(),
+            serializer,
+        )
+    }
+}
+
+/// The envelope as written by [`encode`]: indexed rows are read straight from the driver
+/// rows when given.
+struct StreamedEnvelope<'a> {
+    envelope: &'a Envelope,
+    indexed: Option<IndexedRows<'a>>,
+}
+
+impl Serialize for StreamedEnvelope<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let envelope = self.envelope;
+        match &self.indexed {
+            Some(rows) => envelope.serialize_fields(
+                rows,
+                &rows.decode_warnings(),
+                envelope.raw.as_ref(),
+                serializer,
+            ),
+            None => envelope.serialize(serializer),
+        }
+    }
+}
+
+impl Envelope {
+    pub fn new(engine: Engine, request_id: &RequestId) -> Self {
+        Envelope {
+            version: ENVELOPE_VERSION,
+            engine,
+            columns: Vec::new(),
+            rows: Vec::new(),
+            row_count: 0,
+            truncated: false,
+            elapsed_ms: 0,
+            warnings: Vec::new(),
+            decode_warnings: Vec::new(),
+            request_id: request_id.0.clone(),
+            orient: None,
+            raw: None,
+        }
+    }
+
+    /// Fills the envelope from indexed rows already cut to the limit; `truncated` says
+    /// whether the query returned more. Only CSV and TSV need the rows as JSON values;
+    /// every other format is written by [`encode`] straight from the driver rows. Values
+    /// that fail to decode are reported in `decode_warnings`, or as an error with
+    /// `strict=true`.
+    ///
+    /// Columns are described from the first row, or from `described` when there are no
+    /// rows; only `described` knows nullability.
+    pub fn with_indexed_rows(
+        mut self,
+        rows: &[AnyRow],
+        truncated: bool,
+        described: Option<&Describe<Any>>,
+        options: &QueryOptions,
+    ) -> Result<Self, String> {
+        let decode = options.decode_options();
+        let mut decoder = Decoder::new(decode);
+        self.truncated = truncated;
+        let columns = match (rows.first(), described) {
+            (Some(first), _) => Some(first.columns()),
+            (None, Some(described)) => Some(described.columns()),
+            (None, None) => None,
+        };
+        if let Some(columns) = columns {
+            let names = columns.iter().map(|col| col.name());
+            let names = match decode.duplicates {
+                DuplicatePolicy::Merge => names.map(str::to_string).collect(),
+                DuplicatePolicy::Suffix => sql_to_json::suffixed_names(names),
+                DuplicatePolicy::Error => match sql_to_json::first_duplicate(names.clone()) {
+                    Some(name) => {
+                        return Err(format!(
+                            "Duplicate column name '{name}': alias the columns or pass \
+                             duplicates=merge or duplicates=suffix"
+                        ))
+                    }
+                    None => names.map(str::to_string).collect(),
+                },
+            };
+            self.columns = columns
+                .iter()
+                .zip(names)
+                .map(|(col, name)| Column {
+                    name,
+                    type_name: col.type_info().name().to_string(),
+                    logical_type: sql_to_json::logical_type(col.type_info()),
+                    nullable: described.and_then(|d| d.nullable(col.ordinal())),
+                    unsupported: !sql_to_json::is_supported(col.type_info()),
+                })
+                .collect();
+        }
+        self.row_count = rows.len();
+        if decode.tz.is_some() {
+            let untouched: Vec<&str> = self
+                .columns
+                .iter()
+                .filter(|c| matches!(c.logical_type.as_str(), "date" | "time"))
+                .map(|c| c.name.as_str())
+                .collect();
+            if !untouched.is_empty() {
+                self.warnings.push(format!(
+                    "tz is not applied to date and time columns: {}",
+                    untouched.join(", ")
+                ));
+            }
+        }
+        if options.include_raw() {
+            let objects: Vec<Value> = rows
+                .iter()
+                .map(|row| row_to_json_with(row, &decode))
+                .collect();
+            self.raw = Some(json!({
+                "type": "Wql",
+                "data": [{ "result": { "indexed": objects } }]
+            }));
+        }
+        self.rows = match options.output_format() {
+            OutputFormat::Csv | OutputFormat::Tsv => match options.layout() {
+                Layout::ColumnArrays => decoder.column_arrays(rows),
+                Layout::Arrays => decoder.arrays(rows),
+                Layout::Objects => decoder.objects(rows),
+            },
+            // Decoded while `encode` writes them, which collects the warnings
+            _ => Vec::new(),
+        };
+        self.decode_warnings = decoder.into_warnings();
+        if options.is_strict() {
+            if let Some(warning) = self.decode_warnings.first() {
+                return Err(warning.to_string());
+            }
+        }
+        self.set_orient(options);
+        Ok(self)
+    }
+
+    /// Narrows `decimal` columns to the `decimal(p,s)` types declared for their names.
+    pub fn declare_decimals(&mut self, declared: &HashMap<String, String>) {
+        for column in &mut self.columns {
+            if column.logical_type != "decimal" {
+                continue;
+            }
+            if let Some(decimal_type) = declared.get(&column.name) {
+                column.logical_type = decimal_type.clone();
+            }
+        }
+    }
+
+    /// Adds computed columns after the result's own. Rows already decoded (for CSV and
+    /// TSV) get the values too; [`encode`] writes them for every other format.
+    pub fn append_columns(&mut self, columns: Vec<Column>, appended: &[AppendedColumn]) {
+        for (column, appended) in columns.into_iter().zip(appended) {
+            for (row, value) in self.rows.iter_mut().zip(&appended.values) {
+                match row {
+                    Value::Array(cells) => cells.push(value.clone()),
+                    Value::Object(map) => {
+                        map.insert(appended.name.clone(), value.clone());
+                    }
+                    _ => {}
+                }
+            }
+            self.columns.push(column);
+        }
+    }
+
+    /// Writes the envelope with the given `rows`, `decode_warnings` and `raw`. The
+    /// warnings are written after the rows, so that rows decoded while serializing can
+    /// still report theirs.
+    fn serialize_fields<S, R, D, W>(
+        &self,
+        rows: &R,
+        decode_warnings: &D,
+        raw: Option<W>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+        R: Serialize + ?Sized,
+        D: Serialize + ?Sized,
+        W: Serialize,
+    {
+        let len = 10 + usize::from(self.orient.is_some()) + usize::from(self.raw.is_some());
+        let mut state = serializer.serialize_struct("Envelope", len)?;
+        state.serialize_field("version", &self.version)?;
+        state.serialize_field("engine", &self.engine)?;
+        state.serialize_field("columns", &self.columns)?;
+        state.serialize_field("rows", rows)?;
+        state.serialize_field("row_count", &self.row_count)?;
+        state.serialize_field("truncated", &self.truncated)?;
+        state.serialize_field("elapsed_ms", &self.elapsed_ms)?;
+        state.serialize_field("warnings", &self.warnings)?;
+        state.serialize_field("decode_warnings", decode_warnings)?;
+        state.serialize_field("request_id", &self.request_id)?;
+        match &self.orient {
+            Some(orient) => state.serialize_field("orient", orient)?,
+            None => state.skip_field("orient")?,
+        }
+        match &raw {
+            Some(raw) => state.serialize_field("raw", raw)?,
+            None => state.skip_field("raw")?,
+        }
+        state.end()
+    }
+
+    fn set_orient(&mut self, options: &QueryOptions) {
+        if options.output_format() == OutputFormat::Columnar {
+            self.orient = Some(options.orient());
+        }
+    }
+
+    /// Fills the envelope from interpreter results: one row per statement result, or
+    /// the flattened records with `format=table`.
+    pub fn with_interpreter_results<T: Serialize>(
+        mut self,
+        results: &[T],
+        options: &QueryOptions,
+    ) -> Result<Self, serde_json::Error> {
+        let limit = options.row_limit();
+        let values = results
+            .iter()
+            .map(serde_json::to_value)
+            .collect::<Result<Vec<Value>, serde_json::Error>>()?;
+
+        let (mut columns, mut rows) = match options.output_format() {
+            OutputFormat::Json => {
+                let column = Column {
+                    name: "result".to_string(),
+                    type_name: "json".to_string(),
+                    logical_type: "json".to_string(),
+                    nullable: None,
+                    unsupported: false,
+                };
+                let rows = values.iter().map(|v| json!({ "result": v })).collect();
+                (vec![column], rows)
+            }
+            _ => {
+                let table = tabular::normalize(&values);
+                (table.columns, table.rows)
+            }
+        };
+        self.truncated = rows.len() > limit;
+        rows.truncate(limit);
+        self.row_count = rows.len();
+        let decode = options.decode_options();
+        // Epoch fields keep today's numbers unless the request asks for a rendering
+        if options.time_format.is_some() || decode.tz.is_some() {
+            let recognized = tabular::encode_timestamps(&mut columns, &mut rows, &decode);
+            if decode.tz.is_some() && !recognized {
+                self.warnings.push(
+                    "tz was not applied: the results have no `timestamp` or `timestamp_ms` columns"
+                        .to_string(),
+                );
+            }
+        }
+        // Arrow-backed formats keep native integer columns
+        if !options.output_format().arrow_backed() {
+            tabular::encode_bigints(&columns, &mut rows, decode.bigint);
+        }
+        self.rows = match (options.output_format(), options.orient()) {
+            (OutputFormat::Columnar, Orient::Columns) => tabular::to_column_arrays(&columns, &rows),
+            (format, _) if format.positional() => tabular::to_arrays(&columns, &rows),
+            _ => rows,
+        };
+        self.columns = columns;
+        self.set_orient(options);
+        if options.include_raw() {
+            self.raw = Some(Value::Array(values));
+        }
+        Ok(self)
+    }
+}
+
+/// Serializes the envelope in the requested format. `indexed_rows` are the rows the
+/// envelope was filled from, if any; Arrow output types its columns from them.
+/// `appended` are the columns added with [`Envelope::append_columns`].
+pub fn encode(
+    envelope: &Envelope,
+    indexed_rows: &[AnyRow],
+    appended: &[AppendedColumn],
+    options: &QueryOptions,
+) -> Result<Encoded, String> {
+    let filename = options.attachment_name();
+    let (content_type, body) = match options.output_format() {
+        OutputFormat::Csv | OutputFormat::Tsv => {
+            let content_type = if options.output_format() == OutputFormat::Csv {
+                ContentType::CSV
+            } else {
+                ContentType::new("text", "tab-separated-values").with_params(("charset", "utf-8"))
+            };
+            let delimited = options.delimited()?;
+            let body = export::to_delimited(&envelope.columns, &envelope.rows, &delimited)
+                .map_err(|e| e.to_string())?;
+            (content_type, body)
+        }
+        format if format.arrow_backed() => {
+            // Arrow decodes the rows itself; its warnings go in the schema metadata
+            let mut decoder = Decoder::new(options.decode_options());
+            let batch = if envelope.engine == Engine::Indexed {
+                let (columns, appended_columns) = envelope
+                    .columns
+                    .split_at(envelope.columns.len() - appended.len());
+                arrow_export::batch_from_rows(indexed_rows, columns, &mut decoder).and_then(
+                    |batch| arrow_export::append_columns(batch, appended_columns, appended),
+                )
+            } else {
+                arrow_export::batch_from_table(&envelope.columns, &envelope.rows)
+            }
+            .map_err(|e| e.to_string())?;
+            let decode_warnings = decoder.into_warnings();
+            if options.is_strict() {
+                if let Some(warning) = decode_warnings.first() {
+                    return Err(warning.to_string());
+                }
+            }
+            let mut metadata = HashMap::from([
+                ("engine".to_string(), envelope.engine.as_str().to_string()),
+                ("request_id".to_string(), envelope.request_id.clone()),
+                ("truncated".to_string(), envelope.truncated.to_string()),
+            ]);
+            if !decode_warnings.is_empty() {
+                let warnings =
+                    serde_json::to_string(&decode_warnings).map_err(|e| e.to_string())?;
+                metadata.insert("decode_warnings".to_string(), warnings);
+            }
+            let batch = arrow_export::with_metadata(batch, metadata).map_err(|e| e.to_string())?;
+            if format == OutputFormat::Parquet {
+                let body = arrow_export::to_parquet(&batch, &options.parquet()?)
+                    .map_err(|e| e.to_string())?;
+                (ContentType::new("application", "vnd.apache.parquet"), body)
+            } else {
+                let body = arrow_export::to_ipc_stream(&batch).map_err(|e| e.to_string())?;
+                let (top, sub) = ARROW_STREAM_MEDIA_TYPE;
+                (ContentType::new(top, sub), body)
+            }
+        }
+        _ => {
+            let wire = options.envelope_encoding().unwrap_or_default();
+            let native = wire != Encoding::Json;
+            // Interpreter results have no binary columns and are already laid out
+            let streamed = StreamedEnvelope {
+                envelope,
+                indexed: (envelope.engine == Engine::Indexed).then(|| {
+                    IndexedRows::new(indexed_rows, options.layout(), options.decode_options())
+                        .native(native)
+                        .strict(options.is_strict())
+                        .appended(appended)
+                }),
+            };
+            match wire {
+                Encoding::Json => (
+                    ContentType::JSON,
+                    serde_json::to_vec(&streamed).map_err(|e| e.to_string())?,
+                ),
+                Encoding::Msgpack => (
+                    ContentType::MsgPack,
+                    encoding::to_msgpack(&streamed).map_err(|e| e.to_string())?,
+                ),
+                Encoding::Cbor => (
+                    ContentType::new("application", "cbor"),
+                    encoding::to_cbor(&streamed).map_err(|e| e.to_string())?,
+                ),
+            }
+        }
+    };
+    Ok(Encoded {
+        content_type,
+        body,
+        filename,
+    })
+}
+
+/// An encoded response body with its content type and optional download name.
+pub struct Encoded {
+    pub content_type: ContentType,
+    pub body: Vec<u8>,
+    pub filename: Option<String>,
+}
+
+impl<'r> Responder<'r, 'static> for Encoded {
+    fn respond_to(self, _request: &'r Request<'_>) -> response::Result<'static> {
+        let mut builder = Response::build();
+        builder
+            .header(self.content_type)
+            .sized_body(self.body.len(), Cursor::new(self.body));
+        if let Some(filename) = self.filename {
+            builder.raw_header(
+                "Content-Disposition",
+                format!("attachment; filename=\"{}\"", filename),
+            );
+        }
+        builder.ok()
+    }
+}
+
+/// Correlation id for a request: the caller's `X-Request-Id` if present, otherwise a fresh UUID.
+#[derive(Debug, Clone)]
+pub struct RequestId(pub String);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for RequestId {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let id = request
+            .headers()
+            .get_one("X-Request-Id")
+            .filter(|id| !id.is_empty() && id.len() <= 128)
+            .map(str::to_string)
+            .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+        Outcome::Success(RequestId(id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_row_limit_is_capped() {
+        let options = |limit| QueryOptions {
+            limit,
+            ..Default::default()
+        };
+        let max_rows = options(None).row_limit();
+        assert_eq!(options(Some(5)).row_limit(), 5);
+        assert_eq!(options(Some(100_000_000)).row_limit(), max_rows);
+    }
+
+    #[rocket::async_test]
+    async fn test_columns_report_nullability_with_rows() {
+        use sqlx::any::AnyPoolOptions;
+        use sqlx::Executor;
+
+        let pool = AnyPoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        sqlx::query("CREATE TABLE blocks (number INTEGER NOT NULL, miner TEXT)")
+            .execute(&pool)
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO blocks VALUES (1, NULL)")
+            .execute(&pool)
+            .await
+            .unwrap();
+        let sql = "SELECT number, miner FROM blocks";
+        let rows = sqlx::query(sql).fetch_all(&pool).await.unwrap();
+        let described = pool.describe(sql).await.unwrap();
+
+        let envelope = Envelope::new(Engine::Indexed, &RequestId("test".to_string()))
+            .with_indexed_rows(&rows, false, Some(&described), &QueryOptions::default())
+            .unwrap();
+        let nullable: Vec<_> = envelope.columns.iter().map(|c| c.nullable).collect();
+        assert_eq!(nullable, [Some(false), Some(true)]);
+    }
+
+    #[rocket::async_test]
+    async fn test_arrow_reports_decode_failures() {
+        use arrow::array::Array;
+        use arrow::ipc::reader::StreamReader;
+        use sqlx::any::AnyPoolOptions;
+
+        let pool = AnyPoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        sqlx::query("CREATE TABLE events (day DATE, label TEXT)")
+            .execute(&pool)
+            .await
+            .unwrap();
+        // Neither value decodes: the day is not a date and the label is not UTF-8
+        sqlx::query("INSERT INTO events VALUES ('someday', CAST(X'FF' AS TEXT))")
+            .execute(&pool)
+            .await
+            .unwrap();
+        let rows = sqlx::query("SELECT day, label FROM events")
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+
+        let mut options = QueryOptions {
+            format: Some(OutputFormat::Arrow),
+            ..Default::default()
+        };
+        let envelope = Envelope::new(Engine::Indexed, &RequestId("test".to_string()))
+            .with_indexed_rows(&rows, false, None, &options)
+            .unwrap();
+        let encoded = encode(&envelope, &rows, &[], &options).unwrap();
+        let mut reader = StreamReader::try_new(encoded.body.as_slice(), None).unwrap();
+        let warnings: Value =
+            serde_json::from_str(&reader.schema().metadata()["decode_warnings"]).unwrap();
+        let columns: Vec<&Value> = warnings
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|w| &w["column"])
+            .collect();
+        assert_eq!(columns, [&json!("day"), &json!("label")]);
+        let batch = reader.next().unwrap().unwrap();
+        assert!(batch.column(0).is_null(0));
+        assert!(batch.column(1).is_null(0));
+
+        options.strict = Some(true);
+        assert!(encode(&envelope, &rows, &[], &options).is_err());
+    }
+
+    #[rocket::async_test]
+    async fn test_unapplied_tz_is_reported() {
+        use sqlx::any::AnyPoolOptions;
+
+        let options = QueryOptions {
+            tz: Some("Asia/Tokyo".to_string()),
+            ..Default::default()
+        };
+        let request_id = RequestId("test".to_string());
+
+        let pool = AnyPoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        sqlx::query("CREATE TABLE days (day DATE, label TEXT)")
+            .execute(&pool)
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO days VALUES ('2024-03-14', 'pi')")
+            .execute(&pool)
+            .await
+            .unwrap();
+        let rows = sqlx::query("SELECT day, label FROM days")
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        let envelope = Envelope::new(Engine::Indexed, &request_id)
+            .with_indexed_rows(&rows, false, None, &options)
+            .unwrap();
+        assert_eq!(
+            envelope.warnings,
+            ["tz is not applied to date and time columns: day"]
+        );
+
+        let options = QueryOptions {
+            format: Some(OutputFormat::Table),
+            ..options
+        };
+        let results = [json!({"result": {"block": [{"time": 1_710_423_335}]}})];
+        let envelope = Envelope::new(Engine::Eql, &request_id)
+            .with_interpreter_results(&results, &options)
+            .unwrap();
+        assert_eq!(envelope.warnings.len(), 1);
+        let results = [json!({"result": {"block": [{"timestamp": 1_710_423_335}]}})];
+        let envelope = Envelope::new(Engine::Eql, &request_id)
+            .with_interpreter_results(&results, &options)
+            .unwrap();
+        assert!(envelope.warnings.is_empty());
+    }
+}
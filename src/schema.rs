@@ -0,0 +1,405 @@
+use std::collections::{BTreeMap, HashMap};
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use sqlx::any::AnyPool;
+use sqlx::Row;
+use tokio::sync::RwLock;
+use utoipa::ToSchema;
+
+use crate::chains::ChainRegistry;
+use crate::pipeline::SelectColumn;
+
+const DEFAULT_SCHEMA_CACHE_TTL_SECS: u64 = 300;
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ColumnSchema {
+    pub name: String,
+    pub data_type: String,
+    pub nullable: bool,
+    /// Declared precision and scale of `numeric` columns, omitted when unconstrained.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub numeric_precision: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub numeric_scale: Option<i32>,
+}
+
+impl ColumnSchema {
+    /// `decimal(p,s)` for a `numeric` column declared with a precision.
+    pub fn decimal_type(&self) -> Option<String> {
+        let precision = self.numeric_precision?;
+        Some(format!(
+            "decimal({},{})",
+            precision,
+            self.numeric_scale.unwrap_or(0)
+        ))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct TableSchema {
+    /// User-facing name, e.g. `eth.blocks`.
+    pub name: String,
+    pub chain: String,
+    pub table: String,
+    pub columns: Vec<ColumnSchema>,
+    /// Planner estimate from `pg_class.reltuples`, `None` if the table was never analyzed.
+    pub approximate_row_count: Option<i64>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct TablesResponse {
+    pub tables: Vec<TableSchema>,
+}
+
+struct CachedSchema {
+    loaded_at: Instant,
+    tables: BTreeMap<String, TableSchema>,
+}
+
+/// Catalog snapshot of the tables users may query, refreshed lazily after `ttl`.
+pub struct SchemaCache {
+    db_schema: String,
+    ttl: Duration,
+    cached: RwLock<Option<CachedSchema>>,
+}
+
+impl SchemaCache {
+    pub fn new(db_schema: String, ttl: Duration) -> Self {
+        SchemaCache {
+            db_schema,
+            ttl,
+            cached: RwLock::new(None),
+        }
+    }
+
+    pub fn from_env() -> Self {
+        let db_schema = std::env::var("DATABASE_SCHEMA").unwrap_or_else(|_| "public".to_string());
+        let ttl = std::env::var("SCHEMA_CACHE_TTL_SECS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_SCHEMA_CACHE_TTL_SECS);
+        Self::new(db_schema, Duration::from_secs(ttl))
+    }
+
+    pub async fn tables(
+        &self,
+        pool: &AnyPool,
+        chains: &ChainRegistry,
+        refresh: bool,
+    ) -> Result<Vec<TableSchema>, sqlx::Error> {
+        self.with_tables(pool, chains, refresh, |tables| {
+            tables.values().cloned().collect()
+        })
+        .await
+    }
+
+    pub async fn table(
+        &self,
+        pool: &AnyPool,
+        chains: &ChainRegistry,
+        chain: &str,
+        table: &str,
+        refresh: bool,
+    ) -> Result<Option<TableSchema>, sqlx::Error> {
+        let Some(chain) = chains.get(chain) else {
+            return Ok(None);
+        };
+        let name = format!("{}.{}", chain.id, table.to_lowercase());
+        self.with_tables(pool, chains, refresh, |tables| tables.get(&name).cloned())
+            .await
+    }
+
+    /// Declared `decimal(p,s)` types by output column name for a query reading `tables`
+    /// (user-facing names such as `eth.blocks`). Only bare column references get one;
+    /// computed columns and names whose sources disagree are left out.
+    pub async fn decimal_types(
+        &self,
+        pool: &AnyPool,
+        chains: &ChainRegistry,
+        tables: &[&str],
+        select: &[SelectColumn],
+    ) -> Result<HashMap<String, String>, sqlx::Error> {
+        let names: Vec<String> = tables
+            .iter()
+            .filter_map(|name| {
+                let (chain, table) = name.split_once('.')?;
+                Some(format!(
+                    "{}.{}",
+                    chains.get(chain)?.id,
+                    table.to_lowercase()
+                ))
+            })
+            .collect();
+        self.with_tables(pool, chains, false, |tables| {
+            decimal_types(tables, &names, select)
+        })
+        .await
+    }
+
+    async fn with_tables<T>(
+        &self,
+        pool: &AnyPool,
+        chains: &ChainRegistry,
+        refresh: bool,
+        f: impl FnOnce(&BTreeMap<String, TableSchema>) -> T,
+    ) -> Result<T, sqlx::Error> {
+        if !refresh {
+            let cached = self.cached.read().await;
+            if let Some(cached) = cached.as_ref().filter(|c| c.loaded_at.elapsed() < self.ttl) {
+                return Ok(f(&cached.tables));
+            }
+        }
+
+        let mut cached = self.cached.write().await;
+        let tables = load_tables(pool, chains, &self.db_schema).await?;
+        log::info!("Loaded schema for {} exposed tables", tables.len());
+        let result = f(&tables);
+        *cached = Some(CachedSchema {
+            loaded_at: Instant::now(),
+            tables,
+        });
+        Ok(result)
+    }
+}
+
+async fn load_tables(
+    pool: &AnyPool,
+    chains: &ChainRegistry,
+    db_schema: &str,
+) -> Result<BTreeMap<String, TableSchema>, sqlx::Error> {
+    let column_rows = sqlx::query(
+        "SELECT table_name::TEXT AS table_name, column_name::TEXT AS column_name, \
+         data_type::TEXT AS data_type, (is_nullable = 'YES') AS nullable, \
+         CASE WHEN data_type = 'numeric' THEN numeric_precision::INT4 END AS numeric_precision, \
+         CASE WHEN data_type = 'numeric' THEN numeric_scale::INT4 END AS numeric_scale \
+         FROM information_schema.columns \
+         WHERE table_schema = $1 \
+         ORDER BY table_name, ordinal_position",
+    )
+    .bind(db_schema)
+    .fetch_all(pool)
+    .await?;
+
+    let count_rows = sqlx::query(
+        "SELECT c.relname::TEXT AS table_name, c.reltuples::BIGINT AS row_count \
+         FROM pg_class c JOIN pg_namespace n ON n.oid = c.relnamespace \
+         WHERE n.nspname = $1 AND c.relkind IN ('r', 'p', 'm', 'v')",
+    )
+    .bind(db_schema)
+    .fetch_all(pool)
+    .await?;
+
+    let mut row_counts = BTreeMap::new();
+    for row in count_rows {
+        let table_name: String = row.try_get("table_name")?;
+        let row_count: i64 = row.try_get("row_count")?;
+        // reltuples is -1 for tables that have never been vacuumed or analyzed
+        if row_count >= 0 {
+            row_counts.insert(table_name, row_count);
+        }
+    }
+
+    let mut tables: BTreeMap<String, TableSchema> = BTreeMap::new();
+    for row in column_rows {
+        let physical_name: String = row.try_get("table_name")?;
+        let Some((chain, table)) = split_physical_table_name(&physical_name, chains) else {
+            continue;
+        };
+        let name = format!("{}.{}", chain, table);
+        let entry = tables.entry(name.clone()).or_insert_with(|| TableSchema {
+            name,
+            chain: chain.to_string(),
+            table: table.to_string(),
+            columns: Vec::new(),
+            approximate_row_count: row_counts.get(&physical_name).copied(),
+        });
+        entry.columns.push(ColumnSchema {
+            name: row.try_get("column_name")?,
+            data_type: row.try_get("data_type")?,
+            nullable: row.try_get("nullable")?,
+            numeric_precision: row.try_get("numeric_precision")?,
+            numeric_scale: row.try_get("numeric_scale")?,
+        });
+    }
+    Ok(tables)
+}
+
+fn decimal_types(
+    tables: &BTreeMap<String, TableSchema>,
+    names: &[String],
+    select: &[SelectColumn],
+) -> HashMap<String, String> {
+    let columns: Vec<&ColumnSchema> = names
+        .iter()
+        .filter_map(|name| tables.get(name))
+        .flat_map(|table| &table.columns)
+        .collect();
+    let by_column = agreed_types(
+        columns
+            .iter()
+            .map(|column| (column.name.clone(), column.decimal_type())),
+    );
+    let declared = |column: &str| by_column.get(column).cloned().flatten();
+    let outputs = select.iter().flat_map(|item| match item {
+        SelectColumn::Column { name, column } => vec![(name.clone(), declared(column))],
+        SelectColumn::Wildcard => columns
+            .iter()
+            .map(|column| (column.name.clone(), declared(&column.name)))
+            .collect(),
+        SelectColumn::Computed { name } => vec![(name.clone(), None)],
+    });
+    agreed_types(outputs)
+        .into_iter()
+        .filter_map(|(name, decimal_type)| Some((name, decimal_type?)))
+        .collect()
+}
+
+/// Keeps a type per name only where every entry for that name agrees on it.
+fn agreed_types(
+    entries: impl IntoIterator<Item = (String, Option<String>)>,
+) -> HashMap<String, Option<String>> {
+    let mut agreed: HashMap<String, Option<String>> = HashMap::new();
+    for (name, decimal_type) in entries {
+        agreed
+            .entry(name)
+            .and_modify(|existing| {
+                if *existing != decimal_type {
+                    *existing = None;
+                }
+            })
+            .or_insert(decimal_type);
+    }
+    agreed
+}
+
+/// Maps a physical table such as `eth_blocks` back to its user-facing `(chain, table)` pair.
+/// Tables that do not belong to an indexed chain are not exposed.
+pub fn split_physical_table_name<'a>(
+    physical_name: &'a str,
+    chains: &ChainRegistry,
+) -> Option<(&'a str, &'a str)> {
+    let (chain, table) = chains.split_physical(physical_name)?;
+    chain
+        .indexed()
+        .then(|| (&physical_name[..chain.id.len()], table))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipeline;
+
+    #[test]
+    fn test_split_known_chain_table() {
+        let chains = ChainRegistry::builtin();
+        assert_eq!(
+            split_physical_table_name("eth_blocks", &chains),
+            Some(("eth", "blocks"))
+        );
+        assert_eq!(
+            split_physical_table_name("suidev_transaction_blocks", &chains),
+            Some(("suidev", "transaction_blocks"))
+        );
+    }
+
+    #[test]
+    fn test_split_hides_internal_tables() {
+        let chains = ChainRegistry::builtin();
+        assert_eq!(split_physical_table_name("_sqlx_migrations", &chains), None);
+        assert_eq!(split_physical_table_name("users", &chains), None);
+        assert_eq!(split_physical_table_name("eth_", &chains), None);
+        assert_eq!(split_physical_table_name("ethereum_blocks", &chains), None);
+    }
+
+    #[test]
+    fn test_split_chain_ids_with_underscores() {
+        let chains = ChainRegistry::from_json(
+            r#"{"chains": [
+                {"id": "op", "family": "evm", "chain_id": 10, "native_currency": "ETH", "decimals": 18, "availability": "indexed"},
+                {"id": "op_sepolia", "family": "evm", "chain_id": 11155420, "native_currency": "ETH", "decimals": 18, "availability": "indexed"}
+            ]}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            split_physical_table_name("op_sepolia_logs", &chains),
+            Some(("op_sepolia", "logs"))
+        );
+        assert_eq!(
+            split_physical_table_name("op_logs", &chains),
+            Some(("op", "logs"))
+        );
+    }
+
+    #[test]
+    fn test_decimal_types_skip_ambiguous_columns() {
+        let column = |name: &str, precision: Option<i32>, scale: Option<i32>| ColumnSchema {
+            name: name.to_string(),
+            data_type: "numeric".to_string(),
+            nullable: true,
+            numeric_precision: precision,
+            numeric_scale: scale,
+        };
+        let table = |name: &str, columns| TableSchema {
+            name: name.to_string(),
+            chain: "eth".to_string(),
+            table: name[4..].to_string(),
+            columns,
+            approximate_row_count: None,
+        };
+        let tables = BTreeMap::from([
+            (
+                "eth.blocks".to_string(),
+                table(
+                    "eth.blocks",
+                    vec![
+                        column("base_fee", Some(38), Some(0)),
+                        column("value", Some(78), Some(0)),
+                        column("amount", None, None),
+                    ],
+                ),
+            ),
+            (
+                "eth.transactions".to_string(),
+                table(
+                    "eth.transactions",
+                    vec![
+                        column("value", Some(38), Some(18)),
+                        column("fee", Some(20), Some(9)),
+                    ],
+                ),
+            ),
+        ]);
+        let names = ["eth.blocks".to_string(), "eth.transactions".to_string()];
+        let declared = decimal_types(&tables, &names, &[SelectColumn::Wildcard]);
+        assert_eq!(
+            declared.get("base_fee").map(String::as_str),
+            Some("decimal(38,0)")
+        );
+        assert_eq!(
+            declared.get("fee").map(String::as_str),
+            Some("decimal(20,9)")
+        );
+        assert!(!declared.contains_key("value"));
+        assert!(!declared.contains_key("amount"));
+
+        let declared = decimal_types(&tables, &names[..1], &[SelectColumn::Wildcard]);
+        assert_eq!(
+            declared.get("value").map(String::as_str),
+            Some("decimal(78,0)")
+        );
+
+        // Only bare column references carry the declared type
+        let select = pipeline::select_columns(
+            "SELECT SUM(fee) AS fee, t.fee AS f, base_fee FROM eth_transactions t, eth_blocks",
+        )
+        .unwrap();
+        let declared = decimal_types(&tables, &names, &select);
+        assert!(!declared.contains_key("fee"));
+        assert_eq!(declared.get("f").map(String::as_str), Some("decimal(20,9)"));
+        assert_eq!(
+            declared.get("base_fee").map(String::as_str),
+            Some("decimal(38,0)")
+        );
+    }
+}
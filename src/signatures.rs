@@ -0,0 +1,319 @@
+use std::collections::HashMap;
+
+use anyhow::{bail, Context};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::any::AnyRow;
+use tiny_keccak::{Hasher, Keccak};
+use utoipa::ToSchema;
+
+use crate::abi;
+use crate::encoding::AppendedColumn;
+use crate::response::Column;
+use crate::sql_to_json::{suffixed_names, DecodeOptions};
+
+const BUILTIN_SIGNATURES: &str = include_str!("../config/signatures.json");
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum SignatureKind {
+    /// Matched by the first four bytes of calldata.
+    Function,
+    /// Matched by a log's `topic0`.
+    Event,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct Signature {
+    /// Text signature, e.g. `transfer(address,uint256)`.
+    pub signature: String,
+    pub kind: SignatureKind,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct SignaturesResponse {
+    pub hash: String,
+    /// Every known signature with this hash; more than one when the hash is ambiguous.
+    pub signatures: Vec<Signature>,
+}
+
+#[derive(Deserialize)]
+struct SignaturesConfig {
+    #[serde(default)]
+    functions: Vec<String>,
+    #[serde(default)]
+    events: Vec<String>,
+}
+
+/// Function and event signatures by selector and topic hash, for naming calldata and
+/// logs without a contract ABI.
+#[derive(Debug, Default)]
+pub struct SignatureRegistry {
+    functions: HashMap<[u8; 4], Vec<String>>,
+    events: HashMap<[u8; 32], Vec<String>>,
+}
+
+impl SignatureRegistry {
+    /// Reads `{"functions": [...], "events": [...]}` text signatures and hashes them.
+    pub fn from_json(json: &str) -> anyhow::Result<Self> {
+        let config: SignaturesConfig =
+            serde_json::from_str(json).context("Invalid signature database")?;
+        let mut registry = SignatureRegistry::default();
+        for signature in &config.functions {
+            let hash = hash_signature(signature)?;
+            let selector = [hash[0], hash[1], hash[2], hash[3]];
+            add(registry.functions.entry(selector).or_default(), signature);
+        }
+        for signature in &config.events {
+            let hash = hash_signature(signature)?;
+            add(registry.events.entry(hash).or_default(), signature);
+        }
+        Ok(registry)
+    }
+
+    /// The database bundled with the server.
+    pub fn builtin() -> Self {
+        Self::from_json(BUILTIN_SIGNATURES).expect("Bundled signature database must be valid")
+    }
+
+    /// Loads the database from `SIGNATURES_CONFIG` if set, otherwise falls back to the
+    /// bundled one.
+    pub fn from_env() -> anyhow::Result<Self> {
+        match std::env::var("SIGNATURES_CONFIG") {
+            Ok(path) => {
+                let json = std::fs::read_to_string(&path)
+                    .with_context(|| format!("Could not read signature database from {}", path))?;
+                Self::from_json(&json)
+            }
+            Err(_) => Ok(Self::builtin()),
+        }
+    }
+
+    /// Signatures for a `0x` hex 4-byte selector or 32-byte event topic.
+    pub fn lookup(&self, hash: &str) -> Result<Vec<Signature>, String> {
+        let bytes = hex::decode(hash.strip_prefix("0x").unwrap_or(hash)).ok();
+        let (kind, signatures) = match bytes.as_deref() {
+            Some(selector) if selector.len() == 4 => {
+                (SignatureKind::Function, self.functions_for(selector))
+            }
+            Some(topic) if topic.len() == 32 => (SignatureKind::Event, self.events_for(topic)),
+            _ => {
+                return Err(format!(
+                    "Invalid hash '{hash}': expected a 4-byte selector or a 32-byte topic in hex"
+                ))
+            }
+        };
+        Ok(signatures
+            .iter()
+            .map(|signature| Signature {
+                signature: signature.clone(),
+                kind,
+            })
+            .collect())
+    }
+
+    pub fn functions_for(&self, selector: &[u8]) -> &[String] {
+        <[u8; 4]>::try_from(selector)
+            .ok()
+            .and_then(|selector| self.functions.get(&selector))
+            .map_or(&[][..], Vec::as_slice)
+    }
+
+    pub fn events_for(&self, topic: &[u8]) -> &[String] {
+        <[u8; 32]>::try_from(topic)
+            .ok()
+            .and_then(|topic| self.events.get(&topic))
+            .map_or(&[][..], Vec::as_slice)
+    }
+
+    /// Adds `function_signatures` next to every `input` and `event_signatures` next to
+    /// every `topics` found in interpreter results, at any depth. Both are arrays of the
+    /// matching signatures, or null when none is known.
+    pub fn enrich_value(&self, value: &mut Value) {
+        match value {
+            Value::Object(map) => {
+                for item in map.values_mut() {
+                    self.enrich_value(item);
+                }
+                if let Some(input) = map.get("input").and_then(Value::as_str) {
+                    let selector = hex_bytes(input).filter(|input| input.len() >= 4);
+                    let matches = selector.map_or(&[][..], |input| self.functions_for(&input[..4]));
+                    map.insert("function_signatures".to_string(), matches_to_json(matches));
+                }
+                if let Some(topics) = map.get("topics").and_then(Value::as_array) {
+                    let topic0 = topics.first().and_then(Value::as_str).and_then(hex_bytes);
+                    let matches = topic0.map_or(&[][..], |topic| self.events_for(&topic));
+                    map.insert("event_signatures".to_string(), matches_to_json(matches));
+                }
+            }
+            Value::Array(items) => {
+                for item in items {
+                    self.enrich_value(item);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// The `function_signatures` and `event_signatures` columns `enrich=signatures` appends
+    /// to indexed results, named so they do not clash with `taken`. Calldata is read from
+    /// `input` and topics as for `decode=abi`.
+    pub fn enrich_rows(
+        &self,
+        rows: &[AnyRow],
+        taken: &[String],
+        options: &DecodeOptions,
+    ) -> (Vec<Column>, Vec<AppendedColumn>) {
+        let mut functions = Vec::with_capacity(rows.len());
+        let mut events = Vec::with_capacity(rows.len());
+        for row in rows {
+            let selector =
+                abi::column_bytes(row, "input", options).filter(|input| input.len() >= 4);
+            functions.push(match selector {
+                Some(input) => matches_to_json(self.functions_for(&input[..4])),
+                None => Value::Null,
+            });
+            events.push(match abi::topics(row, options) {
+                Some(topics) => matches_to_json(self.events_for(topics[0].as_bytes())),
+                None => Value::Null,
+            });
+        }
+
+        let mut names: Vec<&str> = taken.iter().map(String::as_str).collect();
+        names.extend(["function_signatures", "event_signatures"]);
+        let names = suffixed_names(names.iter().copied());
+        let names = &names[taken.len()..];
+        let columns = names
+            .iter()
+            .map(|name| Column {
+                name: name.clone(),
+                type_name: "json".to_string(),
+                logical_type: "array<text>".to_string(),
+                nullable: Some(true),
+                unsupported: false,
+            })
+            .collect();
+        let appended = names
+            .iter()
+            .zip([functions, events])
+            .map(|(name, values)| AppendedColumn {
+                name: name.clone(),
+                values,
+                json: Vec::new(),
+            })
+            .collect();
+        (columns, appended)
+    }
+}
+
+fn add(signatures: &mut Vec<String>, signature: &str) {
+    if !signatures.iter().any(|known| known == signature) {
+        signatures.push(signature.to_string());
+    }
+}
+
+/// Keccak-256 of a text signature such as `transfer(address,uint256)`.
+fn hash_signature(signature: &str) -> anyhow::Result<[u8; 32]> {
+    let valid = signature
+        .split_once('(')
+        .is_some_and(|(name, _)| !name.is_empty() && signature.ends_with(')'))
+        && !signature.contains(char::is_whitespace);
+    if !valid {
+        bail!(
+            "Invalid signature '{}': expected name(type,...) without spaces",
+            signature
+        );
+    }
+    let mut hash = [0u8; 32];
+    let mut keccak = Keccak::v256();
+    keccak.update(signature.as_bytes());
+    keccak.finalize(&mut hash);
+    Ok(hash)
+}
+
+fn hex_bytes(text: &str) -> Option<Vec<u8>> {
+    hex::decode(text.strip_prefix("0x").unwrap_or(text)).ok()
+}
+
+fn matches_to_json(signatures: &[String]) -> Value {
+    if signatures.is_empty() {
+        Value::Null
+    } else {
+        Value::Array(signatures.iter().cloned().map(Value::String).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_builtin_lookup() {
+        let registry = SignatureRegistry::builtin();
+        let found = registry.lookup("0xa9059cbb").unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].signature, "transfer(address,uint256)");
+        assert_eq!(found[0].kind, SignatureKind::Function);
+
+        let found = registry
+            .lookup("0xddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef")
+            .unwrap();
+        assert_eq!(found[0].signature, "Transfer(address,address,uint256)");
+        assert_eq!(found[0].kind, SignatureKind::Event);
+
+        assert!(registry.lookup("0xdeadbeef").unwrap().is_empty());
+        assert!(registry.lookup("0x1234").is_err());
+    }
+
+    #[test]
+    fn test_ambiguous_selector() {
+        // Both hash to 0x42966c68
+        let registry = SignatureRegistry::from_json(
+            r#"{"functions": ["burn(uint256)", "collate_propagate_storage(bytes16)"]}"#,
+        )
+        .unwrap();
+        let found: Vec<String> = registry
+            .lookup("0x42966c68")
+            .unwrap()
+            .into_iter()
+            .map(|s| s.signature)
+            .collect();
+        assert_eq!(
+            found,
+            vec!["burn(uint256)", "collate_propagate_storage(bytes16)"]
+        );
+    }
+
+    #[test]
+    fn test_invalid_signature_is_rejected() {
+        assert!(
+            SignatureRegistry::from_json(r#"{"functions": ["transfer(address, uint256)"]}"#)
+                .is_err()
+        );
+        assert!(SignatureRegistry::from_json(r#"{"events": ["Transfer"]}"#).is_err());
+    }
+
+    #[test]
+    fn test_enrich_value() {
+        let registry = SignatureRegistry::builtin();
+        let mut value = json!({"result": {"transaction": [
+            {"hash": "0x1", "input": "0xa9059cbb0000"},
+            {"hash": "0x2", "input": "0x"},
+            {"logs": [{"topics": [
+                "0xddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef"
+            ]}]}
+        ]}});
+        registry.enrich_value(&mut value);
+        let transactions = &value["result"]["transaction"];
+        assert_eq!(
+            transactions[0]["function_signatures"],
+            json!(["transfer(address,uint256)"])
+        );
+        assert_eq!(transactions[1]["function_signatures"], Value::Null);
+        assert_eq!(
+            transactions[2]["logs"][0]["event_signatures"],
+            json!(["Transfer(address,address,uint256)"])
+        );
+    }
+}
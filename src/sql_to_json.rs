@@ -1,103 +1,584 @@
-use chrono::{DateTime, FixedOffset, NaiveDateTime};
-use serde_json::{self, Map, Value};
+use std::fmt::Write;
+
+use base64::Engine;
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime, NaiveTime};
+use chrono_tz::Tz;
+use rocket::FromFormField;
+use serde::Serialize;
+use serde_json::value::RawValue;
+use serde_json::{self, Map, Number, Value};
 use sqlx::any::{AnyRow, AnyTypeInfo, AnyTypeInfoKind};
+use sqlx::error::BoxDynError;
 use sqlx::Decode;
 use sqlx::{Column, Row, TypeInfo, ValueRef};
 use log;
+use utoipa::ToSchema;
 
-pub fn row_to_json(row: &AnyRow) -> Value {
-    use Value::Object;
+use crate::pg_binary;
 
-    let columns = row.columns();
-    let mut map = Map::new();
-    for col in columns {
-        let key = col.name().to_string();
-        let value: Value = sql_to_json(row, col);
-        map = add_value_to_map(map, (key, value));
+/// How exact decimals (`NUMERIC`, `DECIMAL`, MSSQL `MONEY`) are written to JSON.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, FromFormField, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum DecimalFormat {
+    /// Exact decimal text, e.g. `"123.4500"`.
+    #[default]
+    String,
+    /// A JSON number with the same digits. Indexed rows written as JSON keep every digit;
+    /// as a [`Value`], a number no `f64` holds exactly stays decimal text.
+    Number,
+}
+
+impl DecimalFormat {
+    /// Converts exact decimal text such as `"-123.4500"` or `"NaN"`.
+    pub fn to_json(self, text: String) -> Value {
+        match self {
+            DecimalFormat::Number => exact_value(&text).unwrap_or(Value::String(text)),
+            DecimalFormat::String => Value::String(text),
+        }
     }
-    Object(map)
 }
 
-pub fn sql_to_json(row: &AnyRow, col: &sqlx::any::AnyColumn) -> Value {
-    let raw_value_result = row.try_get_raw(col.ordinal());
-    match raw_value_result {
-        Ok(raw_value) if !raw_value.is_null() => {
-            let mut raw_value = Some(raw_value);
-            let decoded = sql_nonnull_to_json(|| {
-                raw_value
-                    .take()
-                    .unwrap_or_else(|| row.try_get_raw(col.ordinal()).unwrap())
-            });
-            log::trace!("Decoded value: {decoded:?}");
-            decoded
+/// The decimal as a JSON number, when an `i64`, `u64` or `f64` prints back as `text`.
+fn exact_value(text: &str) -> Option<Value> {
+    if let Ok(int) = text.parse::<i64>() {
+        return Some(int.into());
+    }
+    if let Ok(uint) = text.parse::<u64>() {
+        return Some(uint.into());
+    }
+    text.parse::<f64>()
+        .ok()
+        .and_then(Number::from_f64)
+        .filter(|number| number.to_string() == text)
+        .map(Value::Number)
+}
+
+/// Decimal text as a raw JSON number with exactly its digits, for writers that emit JSON
+/// text. NaN and infinities have no JSON number form.
+pub fn exact_number(text: &str) -> Option<Box<RawValue>> {
+    let unsigned = text.strip_prefix('-').unwrap_or(text);
+    if !unsigned.starts_with(|c: char| c.is_ascii_digit()) {
+        return None;
+    }
+    RawValue::from_string(text.to_string()).ok()
+}
+
+/// Largest integer a JavaScript number holds exactly (2^53 - 1).
+pub const MAX_SAFE_INTEGER: i64 = 9_007_199_254_740_991;
+
+/// How 64-bit integers (`INT8`, `BIGINT`, `BIGINT UNSIGNED`) are written to JSON.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, FromFormField, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum BigIntFormat {
+    /// Always a JSON number.
+    #[default]
+    Number,
+    /// Always a decimal string.
+    String,
+    /// A string only outside `±(2^53 - 1)`, where JavaScript would round it.
+    Auto,
+}
+
+impl BigIntFormat {
+    pub fn to_json(self, n: impl Into<Number>) -> Value {
+        let n = n.into();
+        let stringify = match self {
+            BigIntFormat::Number => false,
+            BigIntFormat::String => true,
+            BigIntFormat::Auto => !n
+                .as_i64()
+                .is_some_and(|i| (-MAX_SAFE_INTEGER..=MAX_SAFE_INTEGER).contains(&i)),
+        };
+        if stringify {
+            Value::String(n.to_string())
+        } else {
+            Value::Number(n)
+        }
+    }
+}
+
+/// How binary columns (`BYTEA`, `BLOB`, `VARBINARY`, ...) are written to JSON.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, FromFormField, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum BinaryFormat {
+    /// `0x`-prefixed lowercase hex, e.g. `"0x68656c6c6f"`.
+    #[default]
+    Hex,
+    /// Standard padded base64, e.g. `"aGVsbG8="`.
+    Base64,
+}
+
+impl BinaryFormat {
+    pub fn encode(self, bytes: &[u8]) -> String {
+        match self {
+            BinaryFormat::Hex => {
+                let mut text = String::with_capacity(2 + bytes.len() * 2);
+                text.push_str("0x");
+                for byte in bytes {
+                    // Writing to a String cannot fail
+                    let _ = write!(text, "{byte:02x}");
+                }
+                text
+            }
+            BinaryFormat::Base64 => base64::engine::general_purpose::STANDARD.encode(bytes),
+        }
+    }
+}
+
+/// How dates, times and timestamps are written to JSON.
+///
+/// Naive timestamps (`TIMESTAMP`, `DATETIME`, `DATETIME2`) are taken to be UTC whenever
+/// they are converted. Dates are midnight UTC as epochs, times of day count from midnight.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, FromFormField, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TimeFormat {
+    /// ISO 8601 text: RFC 3339 timestamps, `2024-01-02` dates and `12:30:00` times.
+    #[default]
+    Rfc3339,
+    /// Whole seconds since the Unix epoch.
+    #[field(value = "epoch_s")]
+    EpochS,
+    /// Milliseconds since the Unix epoch.
+    #[field(value = "epoch_ms")]
+    EpochMs,
+}
+
+impl TimeFormat {
+    /// Writes an instant, in `tz` when given and otherwise in its own offset.
+    pub fn instant_to_json(self, instant: DateTime<FixedOffset>, tz: Option<Tz>) -> Value {
+        match (self, tz) {
+            (TimeFormat::Rfc3339, Some(tz)) => instant.with_timezone(&tz).to_rfc3339().into(),
+            (TimeFormat::Rfc3339, None) => instant.to_rfc3339().into(),
+            (TimeFormat::EpochS, _) => instant.timestamp().into(),
+            (TimeFormat::EpochMs, _) => instant.timestamp_millis().into(),
+        }
+    }
+
+    /// Writes a timestamp without a zone. It keeps its naive ISO form unless a `tz` or an
+    /// epoch format asks for an instant.
+    pub fn naive_to_json(self, naive: NaiveDateTime, tz: Option<Tz>) -> Value {
+        match (self, tz) {
+            (TimeFormat::Rfc3339, None) => naive.format("%FT%T%.f").to_string().into(),
+            _ => self.instant_to_json(naive.and_utc().fixed_offset(), tz),
+        }
+    }
+
+    pub fn date_to_json(self, date: NaiveDate) -> Value {
+        match self {
+            TimeFormat::Rfc3339 => date.to_string().into(),
+            _ => {
+                let midnight = date.and_time(NaiveTime::MIN).and_utc();
+                self.instant_to_json(midnight.fixed_offset(), None)
+            }
         }
-        Ok(_null) => Value::Null,
-        Err(e) => {
-            log::warn!("Unable to extract value from row: {e:?}");
-            Value::Null
+    }
+
+    pub fn time_to_json(self, time: NaiveTime) -> Value {
+        let since_midnight = time - NaiveTime::MIN;
+        match self {
+            TimeFormat::Rfc3339 => time.to_string().into(),
+            TimeFormat::EpochS => since_midnight.num_seconds().into(),
+            TimeFormat::EpochMs => since_midnight.num_milliseconds().into(),
+        }
+    }
+}
+
+/// What object rows do with several columns of the same name, e.g. `SELECT a.id, b.id`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, FromFormField, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum DuplicatePolicy {
+    /// One key holding an array of the values, in select-list order. Array values are
+    /// spliced into it rather than nested.
+    #[default]
+    Merge,
+    /// Later columns are renamed `id_2`, `id_3`, ...
+    Suffix,
+    /// The query is rejected.
+    Error,
+}
+
+/// Choices that change how rows and individual values are converted to JSON.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct DecodeOptions {
+    pub decimal: DecimalFormat,
+    pub bigint: BigIntFormat,
+    pub binary: BinaryFormat,
+    pub duplicates: DuplicatePolicy,
+    pub time_format: TimeFormat,
+    /// Zone that instants are rendered in with `time_format=rfc3339`.
+    pub tz: Option<Tz>,
+}
+
+/// A value that failed to decode and was returned as `null` instead.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, ToSchema)]
+pub struct DecodeWarning {
+    /// Zero-based index of the row in the result.
+    pub row: usize,
+    pub column: String,
+    /// Backend type name of the column.
+    #[serde(rename = "type")]
+    pub type_name: String,
+    pub error: String,
+}
+
+impl DecodeWarning {
+    pub fn new(row: usize, col: &sqlx::any::AnyColumn, error: impl std::fmt::Display) -> Self {
+        DecodeWarning {
+            row,
+            column: col.name().to_string(),
+            type_name: col.type_info().name().to_string(),
+            error: error.to_string(),
         }
     }
 }
 
-fn decode_raw<'a, T: Decode<'a, sqlx::any::Any> + Default>(
+impl std::fmt::Display for DecodeWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Failed to decode {} value in row {}, column '{}': {}",
+            self.type_name, self.row, self.column, self.error
+        )
+    }
+}
+
+/// Converts rows to JSON, replacing values that fail to decode with `null` and
+/// recording a [`DecodeWarning`] for each of them.
+pub struct Decoder {
+    options: DecodeOptions,
+    warnings: Vec<DecodeWarning>,
+}
+
+impl Decoder {
+    pub fn new(options: DecodeOptions) -> Self {
+        Decoder {
+            options,
+            warnings: Vec::new(),
+        }
+    }
+
+    /// The value of `col` in the row at `index`.
+    pub fn value(&mut self, index: usize, row: &AnyRow, col: &sqlx::any::AnyColumn) -> Value {
+        self.try_value(index, row, col).unwrap_or_else(|warning| self.skip(warning))
+    }
+
+    /// The value of `col` in the row at `index`, or the warning to report for it.
+    pub fn try_value(
+        &self,
+        index: usize,
+        row: &AnyRow,
+        col: &sqlx::any::AnyColumn,
+    ) -> Result<Value, DecodeWarning> {
+        try_sql_to_json(row, col, &self.options).map_err(|e| DecodeWarning::new(index, col, e))
+    }
+
+    /// Records a value that failed to decode and returns the `null` written in its place.
+    pub fn skip(&mut self, warning: DecodeWarning) -> Value {
+        log::warn!("{warning}");
+        self.warnings.push(warning);
+        Value::Null
+    }
+
+    /// The row as an object with keys in select-list order. Duplicate column names are
+    /// merged into arrays or suffixed following the [`DuplicatePolicy`]; `error` is left to
+    /// the caller to enforce before decoding, and suffixes here.
+    pub fn object(&mut self, index: usize, row: &AnyRow) -> Value {
+        let columns = row.columns();
+        let mut map = Map::with_capacity(columns.len());
+        if self.options.duplicates == DuplicatePolicy::Merge {
+            for col in columns {
+                let value = self.value(index, row, col);
+                map = add_value_to_map(map, (col.name().to_string(), value));
+            }
+        } else {
+            let keys = suffixed_names(columns.iter().map(|col| col.name()));
+            for (col, key) in columns.iter().zip(keys) {
+                map.insert(key, self.value(index, row, col));
+            }
+        }
+        Value::Object(map)
+    }
+
+    pub fn objects(&mut self, rows: &[AnyRow]) -> Vec<Value> {
+        rows.iter()
+            .enumerate()
+            .map(|(index, row)| self.object(index, row))
+            .collect()
+    }
+
+    /// Row-major positional arrays in select-list order, read straight from each row's columns.
+    pub fn arrays(&mut self, rows: &[AnyRow]) -> Vec<Value> {
+        rows.iter()
+            .enumerate()
+            .map(|(index, row)| {
+                Value::Array(
+                    row.columns()
+                        .iter()
+                        .map(|col| self.value(index, row, col))
+                        .collect(),
+                )
+            })
+            .collect()
+    }
+
+    /// Column-major arrays: one array per column, each holding that column's value for every row.
+    pub fn column_arrays(&mut self, rows: &[AnyRow]) -> Vec<Value> {
+        let Some(first) = rows.first() else {
+            return Vec::new();
+        };
+        first
+            .columns()
+            .iter()
+            .map(|col| {
+                Value::Array(
+                    rows.iter()
+                        .enumerate()
+                        .map(|(index, row)| self.value(index, row, col))
+                        .collect(),
+                )
+            })
+            .collect()
+    }
+
+    pub fn warnings(&self) -> &[DecodeWarning] {
+        &self.warnings
+    }
+
+    pub fn into_warnings(self) -> Vec<DecodeWarning> {
+        self.warnings
+    }
+}
+
+pub fn row_to_json(row: &AnyRow) -> Value {
+    row_to_json_with(row, &DecodeOptions::default())
+}
+
+pub fn row_to_json_with(row: &AnyRow, options: &DecodeOptions) -> Value {
+    Decoder::new(*options).object(0, row)
+}
+
+pub fn sql_to_json(row: &AnyRow, col: &sqlx::any::AnyColumn) -> Value {
+    sql_to_json_with(row, col, &DecodeOptions::default())
+}
+
+/// Like [`try_sql_to_json`], with values that fail to decode logged and returned as `null`.
+pub fn sql_to_json_with(
+    row: &AnyRow,
+    col: &sqlx::any::AnyColumn,
+    options: &DecodeOptions,
+) -> Value {
+    try_sql_to_json(row, col, options).unwrap_or_else(|e| {
+        log::warn!("Unable to decode column {:?}: {e}", col.name());
+        Value::Null
+    })
+}
+
+pub fn try_sql_to_json(
+    row: &AnyRow,
+    col: &sqlx::any::AnyColumn,
+    options: &DecodeOptions,
+) -> Result<Value, BoxDynError> {
+    let raw_value = row.try_get_raw(col.ordinal())?;
+    if raw_value.is_null() {
+        return Ok(Value::Null);
+    }
+    let mut raw_value = Some(raw_value);
+    let decoded = try_sql_nonnull_to_json(
+        || {
+            raw_value
+                .take()
+                .unwrap_or_else(|| row.try_get_raw(col.ordinal()).unwrap())
+        },
+        options,
+    )?;
+    log::trace!("Decoded value: {decoded:?}");
+    Ok(decoded)
+}
+
+fn decode_raw<'a, T: Decode<'a, sqlx::any::Any>>(
     raw_value: sqlx::any::AnyValueRef<'a>,
-) -> T {
-    match T::decode(raw_value) {
-        Ok(v) => v,
-        Err(e) => {
-            let type_name = std::any::type_name::<T>();
-            log::error!("Failed to decode {type_name} value: {e}");
-            T::default()
+) -> Result<T, BoxDynError> {
+    T::decode(raw_value)
+}
+
+/// Exact decimal text for a `NUMERIC`/`DECIMAL`/`MONEY` value, without going through `f64`.
+pub fn decode_decimal(raw_value: sqlx::any::AnyValueRef<'_>) -> Result<String, BoxDynError> {
+    let kind = raw_value.type_info().into_owned().0;
+    match kind {
+        AnyTypeInfoKind::Postgres(_) => {
+            let bytes = <Vec<u8> as Decode<sqlx::any::Any>>::decode(raw_value)?;
+            pg_binary::numeric_to_string(&bytes)
+        }
+        // MySQL sends DECIMAL as its exact text representation
+        AnyTypeInfoKind::MySql(_) => <String as Decode<sqlx::any::Any>>::decode(raw_value),
+        AnyTypeInfoKind::Mssql(_) => {
+            Ok(<sqlx::types::Decimal as Decode<sqlx::any::Any>>::decode(raw_value)?.to_string())
+        }
+        // SQLite has no decimal storage class: NUMERIC values are stored as INTEGER or REAL
+        AnyTypeInfoKind::Sqlite(_) => {
+            Ok(<f64 as Decode<sqlx::any::Any>>::decode(raw_value)?.to_string())
         }
     }
 }
 
-pub fn sql_nonnull_to_json<'r>(mut get_ref: impl FnMut() -> sqlx::any::AnyValueRef<'r>) -> Value {
+/// Backend-independent name for the JSON values a column of this type decodes to:
+/// `int64`, `uint64`, `decimal`, `float`, `bool`, `text`, `bytes`, `date`, `time`,
+/// `timestamp`, `timestamptz`, `json` or `array<...>` of one of these. Postgres types
+/// without a decoder are `bytes`, since their encoded form is returned; unrecognized types
+/// of other backends are `unknown` and returned as the driver's text. The drivers do not
+/// report decimal precision and scale (sqlx drops the Postgres type modifier), so decimals
+/// are plain `decimal`; the Postgres catalog can narrow them to `decimal(p,s)`, see
+/// `SchemaCache::decimal_types` in the server.
+pub fn logical_type(type_info: &AnyTypeInfo) -> String {
+    if let AnyTypeInfo(AnyTypeInfoKind::Postgres(pg)) = type_info {
+        if let Some(logical) = pg_binary::logical_type(pg) {
+            return logical;
+        }
+        if !pg_binary::is_supported(pg) {
+            return "bytes".to_string();
+        }
+    }
+    let logical = match type_info.name() {
+        "REAL" | "FLOAT" | "FLOAT4" | "FLOAT8" | "DOUBLE" => "float",
+        "NUMERIC" | "DECIMAL" | "MONEY" | "SMALLMONEY" => "decimal",
+        "INT8" | "BIGINT" | "SERIAL8" | "BIGSERIAL" | "IDENTITY" | "INT64" | "INTEGER8"
+        | "BIGINT SIGNED" | "INT" | "INT4" | "INTEGER" | "MEDIUMINT" | "YEAR" | "INT2"
+        | "SMALLINT" | "TINYINT" | "INT UNSIGNED" | "MEDIUMINT UNSIGNED"
+        | "SMALLINT UNSIGNED" | "TINYINT UNSIGNED" => "int64",
+        "BIGINT UNSIGNED" => "uint64",
+        "BOOL" | "BOOLEAN" => "bool",
+        "BIT" => match type_info {
+            AnyTypeInfo(AnyTypeInfoKind::Mssql(_)) => "bool",
+            AnyTypeInfo(AnyTypeInfoKind::MySql(mysql_type)) if mysql_type.max_size() == Some(1) => {
+                "bool"
+            }
+            AnyTypeInfo(AnyTypeInfoKind::MySql(_)) => "uint64",
+            _ => "text",
+        },
+        "DATE" => "date",
+        "TIME" | "TIMETZ" => "time",
+        "DATETIMEOFFSET" | "TIMESTAMPTZ" => "timestamptz",
+        "TIMESTAMP" | "DATETIME" | "DATETIME2" => "timestamp",
+        "JSON" | "JSONB" => "json",
+        "JSON[]" | "JSONB[]" => "array<json>",
+        _ if is_binary(type_info) => "bytes",
+        _ if is_text(type_info) => "text",
+        _ => "unknown",
+    };
+    logical.to_string()
+}
+
+/// Whether values of this type have a decoder. Unsupported Postgres types are passed
+/// through as encoded wire bytes; other backends fall back to the driver's text.
+pub fn is_supported(type_info: &AnyTypeInfo) -> bool {
+    match type_info {
+        AnyTypeInfo(AnyTypeInfoKind::Postgres(pg)) => pg_binary::is_supported(pg),
+        _ => logical_type(type_info) != "unknown",
+    }
+}
+
+/// Whether values of this type are text, for the types not matched by name before it in
+/// [`logical_type`]. Every remaining supported Postgres type decodes to text.
+fn is_text(type_info: &AnyTypeInfo) -> bool {
+    match type_info {
+        AnyTypeInfo(AnyTypeInfoKind::Postgres(_)) => true,
+        _ => matches!(
+            type_info.name(),
+            "NULL"
+                | "TEXT"
+                | "CHAR"
+                | "VARCHAR"
+                | "TINYTEXT"
+                | "MEDIUMTEXT"
+                | "LONGTEXT"
+                | "ENUM"
+                | "SET"
+                | "NCHAR"
+                | "NVARCHAR"
+                | "NTEXT"
+                | "BIGCHAR"
+                | "BIGVARCHAR"
+                | "XML"
+        ),
+    }
+}
+
+/// Whether values of this type are raw bytes rather than text.
+pub fn is_binary(type_info: &AnyTypeInfo) -> bool {
+    matches!(
+        type_info.name(),
+        "BYTEA" | "BLOB" | "TINYBLOB" | "MEDIUMBLOB" | "LONGBLOB" | "BINARY" | "VARBINARY" | "IMAGE"
+    )
+}
+
+fn decimal_to_json(
+    raw_value: sqlx::any::AnyValueRef<'_>,
+    options: &DecodeOptions,
+) -> Result<Value, BoxDynError> {
+    Ok(options.decimal.to_json(decode_decimal(raw_value)?))
+}
+
+pub fn try_sql_nonnull_to_json<'r>(
+    mut get_ref: impl FnMut() -> sqlx::any::AnyValueRef<'r>,
+    options: &DecodeOptions,
+) -> Result<Value, BoxDynError> {
     let raw_value = get_ref();
-    let type_info = raw_value.type_info();
+    // Owned, so that arms can still use it after `raw_value` is consumed by a decoder
+    let type_info = raw_value.type_info().into_owned();
     let type_name = type_info.name();
     log::trace!("Decoding a value of type {type_name:?} (type info: {type_info:?})");
-    match type_name {
-        "REAL" | "FLOAT" | "FLOAT4" | "FLOAT8" | "DOUBLE" | "NUMERIC" | "DECIMAL" => {
-            decode_raw::<f64>(raw_value).into()
+    Ok(match type_name {
+        "REAL" | "FLOAT" | "FLOAT4" | "FLOAT8" | "DOUBLE" => decode_raw::<f64>(raw_value)?.into(),
+        "NUMERIC" | "DECIMAL" => decimal_to_json(raw_value, options)?,
+        "MONEY" | "SMALLMONEY" if matches!(type_info, AnyTypeInfo(AnyTypeInfoKind::Mssql(_))) => {
+            decimal_to_json(raw_value, options)?
         }
         "INT8" | "BIGINT" | "SERIAL8" | "BIGSERIAL" | "IDENTITY" | "INT64" | "INTEGER8"
-        | "BIGINT SIGNED" => decode_raw::<i64>(raw_value).into(),
-        "INT" | "INT4" | "INTEGER" | "MEDIUMINT" | "YEAR" => decode_raw::<i32>(raw_value).into(),
-        "INT2" | "SMALLINT" | "TINYINT" => decode_raw::<i16>(raw_value).into(),
-        "BIGINT UNSIGNED" => decode_raw::<u64>(raw_value).into(),
+        | "BIGINT SIGNED" => options.bigint.to_json(decode_raw::<i64>(raw_value)?),
+        "INT" | "INT4" | "INTEGER" | "MEDIUMINT" | "YEAR" => decode_raw::<i32>(raw_value)?.into(),
+        "INT2" | "SMALLINT" | "TINYINT" => decode_raw::<i16>(raw_value)?.into(),
+        "BIGINT UNSIGNED" => options.bigint.to_json(decode_raw::<u64>(raw_value)?),
         "INT UNSIGNED" | "MEDIUMINT UNSIGNED" | "SMALLINT UNSIGNED" | "TINYINT UNSIGNED" => {
-            decode_raw::<u32>(raw_value).into()
+            decode_raw::<u32>(raw_value)?.into()
         }
-        "BOOL" | "BOOLEAN" => decode_raw::<bool>(raw_value).into(),
-        "BIT" if matches!(*type_info, AnyTypeInfo(AnyTypeInfoKind::Mssql(_))) => {
-            decode_raw::<bool>(raw_value).into()
+        "BOOL" | "BOOLEAN" => decode_raw::<bool>(raw_value)?.into(),
+        "BIT" if matches!(type_info, AnyTypeInfo(AnyTypeInfoKind::Mssql(_))) => {
+            decode_raw::<bool>(raw_value)?.into()
         }
-        "BIT" if matches!(*type_info, AnyTypeInfo(AnyTypeInfoKind::MySql(ref mysql_type)) if mysql_type.max_size() == Some(1)) => {
-            decode_raw::<bool>(raw_value).into()
+        "BIT" if matches!(type_info, AnyTypeInfo(AnyTypeInfoKind::MySql(ref mysql_type)) if mysql_type.max_size() == Some(1)) => {
+            decode_raw::<bool>(raw_value)?.into()
         }
-        "BIT" if matches!(*type_info, AnyTypeInfo(AnyTypeInfoKind::MySql(_))) => {
-            decode_raw::<u64>(raw_value).into()
+        "BIT" if matches!(type_info, AnyTypeInfo(AnyTypeInfoKind::MySql(_))) => {
+            decode_raw::<u64>(raw_value)?.into()
         }
-        "DATE" => decode_raw::<chrono::NaiveDate>(raw_value)
-            .to_string()
-            .into(),
-        "TIME" | "TIMETZ" => decode_raw::<chrono::NaiveTime>(raw_value)
-            .to_string()
-            .into(),
-        "DATETIMEOFFSET" | "TIMESTAMP" | "TIMESTAMPTZ" => {
-            decode_raw::<DateTime<FixedOffset>>(raw_value)
-                .to_rfc3339()
-                .into()
+        "DATE" => options.time_format.date_to_json(decode_raw(raw_value)?),
+        "TIME" | "TIMETZ" => options.time_format.time_to_json(decode_raw(raw_value)?),
+        "DATETIMEOFFSET" | "TIMESTAMP" | "TIMESTAMPTZ" => options
+            .time_format
+            .instant_to_json(decode_raw(raw_value)?, options.tz),
+        "DATETIME" | "DATETIME2" => options
+            .time_format
+            .naive_to_json(decode_raw(raw_value)?, options.tz),
+        _ if pg_binary::is_container(&type_info) => {
+            let bytes = decode_raw::<Vec<u8>>(raw_value)?;
+            pg_binary::container_to_json(&type_info, &bytes, options)?
         }
-        "DATETIME" | "DATETIME2" => decode_raw::<NaiveDateTime>(raw_value)
-            .format("%FT%T%.f")
-            .to_string()
+        "JSON" | "JSON[]" | "JSONB" | "JSONB[]" => decode_raw::<Value>(raw_value)?,
+        _ if is_binary(&type_info) => options
+            .binary
+            .encode(&decode_raw::<Vec<u8>>(raw_value)?)
             .into(),
-        "JSON" | "JSON[]" | "JSONB" | "JSONB[]" => decode_raw::<Value>(raw_value),
+        // Every other Postgres type is decoded from its binary form by OID
+        _ if matches!(type_info, AnyTypeInfo(AnyTypeInfoKind::Postgres(_))) => {
+            let AnyTypeInfo(AnyTypeInfoKind::Postgres(pg)) = &type_info else {
+                unreachable!()
+            };
+            let bytes = decode_raw::<Vec<u8>>(raw_value)?;
+            pg_binary::value_to_json(pg, &bytes, options)?
+        }
         // Deserialize as a string by default
-        _ => decode_raw::<String>(raw_value).into(),
-    }
+        _ => decode_raw::<String>(raw_value)?.into(),
+    })
 }
 
 /// Takes the first column of a row and converts it to a string.
@@ -136,6 +617,34 @@ pub fn add_value_to_map(
     map
 }
 
+/// The first name that appears more than once.
+pub fn first_duplicate<'a>(names: impl IntoIterator<Item = &'a str>) -> Option<&'a str> {
+    let mut seen = std::collections::HashSet::new();
+    names.into_iter().find(|name| !seen.insert(*name))
+}
+
+/// Names made unique by renaming repeats to `name_2`, `name_3`, ..., skipping suffixed
+/// names that are already taken by another column.
+pub fn suffixed_names<'a>(names: impl IntoIterator<Item = &'a str> + Clone) -> Vec<String> {
+    let mut taken: std::collections::HashSet<String> =
+        names.clone().into_iter().map(str::to_string).collect();
+    let mut seen = std::collections::HashSet::new();
+    names
+        .into_iter()
+        .map(|name| {
+            if seen.insert(name) {
+                return name.to_string();
+            }
+            let unique = (2..)
+                .map(|n| format!("{name}_{n}"))
+                .find(|candidate| !taken.contains(candidate))
+                .unwrap();
+            taken.insert(unique.clone());
+            unique
+        })
+        .collect()
+}
+
 #[warn(dead_code)]
 pub fn test_database_url() -> String {
     std::env::var("DATABASE_URL").unwrap_or_else(|_| "sqlite::memory:".to_string())
@@ -185,10 +694,17 @@ mod tests {
         )
         .fetch_one(&mut c)
         .await?;
+        // Only SQLite reads the literal as REAL; elsewhere it is an exact decimal, written
+        // as a string by default
+        let one_value = if db_url.starts_with("sqlite") {
+            serde_json::json!(123.456)
+        } else {
+            serde_json::json!("123.456")
+        };
         assert_eq!(
             row_to_json(&row),
             serde_json::json!({
-                "one_value": 123.456,
+                "one_value": one_value,
                 "two_values": [1,2],
                 "three_values": ["x","y","z"],
             })
@@ -196,6 +712,230 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_row_to_json_keeps_select_order() -> anyhow::Result<()> {
+        let db_url = test_database_url();
+        let mut c = sqlx::AnyConnection::connect(&db_url).await?;
+        let row = sqlx::query("SELECT 1 as zeta, 2 as alpha, 3 as mid")
+            .fetch_one(&mut c)
+            .await?;
+        assert_eq!(
+            serde_json::to_string(&row_to_json(&row))?,
+            r#"{"zeta":1,"alpha":2,"mid":3}"#
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_decimal_format() {
+        let number = |text: &str| DecimalFormat::Number.to_json(text.to_string());
+        assert_eq!(number("-42"), serde_json::json!(-42));
+        assert_eq!(number("18446744073709551615"), serde_json::json!(u64::MAX));
+        assert_eq!(number("123.456"), serde_json::json!(123.456));
+        // No f64 prints these digits back, so a `Value` keeps the text
+        assert_eq!(number("123.4500"), serde_json::json!("123.4500"));
+        assert_eq!(
+            number(
+                "115792089237316195423570985008687907853269984665640564039457584007913129639935"
+            ),
+            serde_json::json!(
+                "115792089237316195423570985008687907853269984665640564039457584007913129639935"
+            )
+        );
+        assert_eq!(number("NaN"), serde_json::json!("NaN"));
+        assert_eq!(
+            DecimalFormat::String.to_json("1.50".to_string()),
+            serde_json::json!("1.50")
+        );
+
+        assert_eq!(exact_number("-123.4500").unwrap().get(), "-123.4500");
+        assert!(exact_number("NaN").is_none());
+        assert!(exact_number("-Infinity").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_duplicate_policies() -> anyhow::Result<()> {
+        let db_url = test_database_url();
+        let mut c = sqlx::AnyConnection::connect(&db_url).await?;
+        let row = sqlx::query("SELECT 1 as id, 'x' as name, 2 as id, 3 as id_2")
+            .fetch_one(&mut c)
+            .await?;
+        assert_eq!(
+            serde_json::to_string(&row_to_json(&row))?,
+            r#"{"id":[1,2],"name":"x","id_2":3}"#
+        );
+        let options = DecodeOptions {
+            duplicates: DuplicatePolicy::Suffix,
+            ..DecodeOptions::default()
+        };
+        assert_eq!(
+            serde_json::to_string(&row_to_json_with(&row, &options))?,
+            r#"{"id":1,"name":"x","id_3":2,"id_2":3}"#
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_postgres_merge_splices_arrays() -> anyhow::Result<()> {
+        let Some(db_url) = db_specific_test("postgres") else {
+            return Ok(());
+        };
+        let mut c = sqlx::AnyConnection::connect(&db_url).await?;
+        let rows = sqlx::query("SELECT ARRAY[1, 2] AS v, 3 AS v, ARRAY[4] AS v")
+            .fetch_all(&mut c)
+            .await?;
+        let expected = serde_json::json!([{"v": [1, 2, 3, 4]}]);
+        assert_eq!(
+            Value::Array(Decoder::new(DecodeOptions::default()).objects(&rows)),
+            expected
+        );
+        let streamed = crate::encoding::IndexedRows::new(
+            &rows,
+            crate::encoding::Layout::Objects,
+            DecodeOptions::default(),
+        );
+        assert_eq!(serde_json::to_value(&streamed)?, expected);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_logical_types() -> anyhow::Result<()> {
+        let Some(db_url) = db_specific_test("sqlite") else {
+            return Ok(());
+        };
+        let mut c = sqlx::AnyConnection::connect(&db_url).await?;
+        sqlx::query("CREATE TEMPORARY TABLE typed (n INTEGER, d DATE, b BLOB, t TEXT)")
+            .execute(&mut c)
+            .await?;
+        // Zero rows: columns only come from describing the statement
+        let described = sqlx::Executor::describe(&mut c, "SELECT n, d, b, t FROM typed").await?;
+        let types: Vec<String> = described
+            .columns()
+            .iter()
+            .map(|col| logical_type(col.type_info()))
+            .collect();
+        assert_eq!(types, vec!["int64", "date", "bytes", "text"]);
+        assert!(described
+            .columns()
+            .iter()
+            .all(|col| is_supported(col.type_info())));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_postgres_logical_types() -> anyhow::Result<()> {
+        let Some(db_url) = db_specific_test("postgres") else {
+            return Ok(());
+        };
+        let mut c = sqlx::AnyConnection::connect(&db_url).await?;
+        let row = sqlx::query(
+            "SELECT 1::INT2, 2::NUMERIC, now(), '1 day'::INTERVAL, ARRAY[1]::INT8[], \
+             ARRAY['{}']::JSONB[], ROW(1, 'x'), '\\x00'::BYTEA, 'x'::TEXT, 'a b'::TSVECTOR",
+        )
+        .fetch_one(&mut c)
+        .await?;
+        let types: Vec<String> = row
+            .columns()
+            .iter()
+            .map(|col| logical_type(col.type_info()))
+            .collect();
+        assert_eq!(
+            types,
+            vec![
+                "int64",
+                "decimal",
+                "timestamptz",
+                "json",
+                "array<int64>",
+                "array<json>",
+                "json",
+                "bytes",
+                "text",
+                "bytes"
+            ]
+        );
+        let unsupported: Vec<&str> = row
+            .columns()
+            .iter()
+            .filter(|col| !is_supported(col.type_info()))
+            .map(|col| col.type_info().name())
+            .collect();
+        assert_eq!(unsupported, ["TSVECTOR"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_time_format() {
+        let instant = DateTime::parse_from_rfc3339("2024-03-14T13:14:15.5+01:00").unwrap();
+        let paris = Some(chrono_tz::Europe::Paris);
+        let tokyo = Some(chrono_tz::Asia::Tokyo);
+        assert_eq!(
+            TimeFormat::Rfc3339.instant_to_json(instant, None),
+            "2024-03-14T13:14:15.500+01:00"
+        );
+        assert_eq!(
+            TimeFormat::Rfc3339.instant_to_json(instant, tokyo),
+            "2024-03-14T21:14:15.500+09:00"
+        );
+        assert_eq!(
+            TimeFormat::EpochS.instant_to_json(instant, tokyo),
+            1_710_418_455
+        );
+        assert_eq!(
+            TimeFormat::EpochMs.instant_to_json(instant, None),
+            1_710_418_455_500_i64
+        );
+
+        let naive = instant.naive_utc();
+        assert_eq!(
+            TimeFormat::Rfc3339.naive_to_json(naive, None),
+            "2024-03-14T12:14:15.500"
+        );
+        assert_eq!(
+            TimeFormat::Rfc3339.naive_to_json(naive, paris),
+            "2024-03-14T13:14:15.500+01:00"
+        );
+        let date = naive.date();
+        assert_eq!(TimeFormat::Rfc3339.date_to_json(date), "2024-03-14");
+        assert_eq!(TimeFormat::EpochS.date_to_json(date), 1_710_374_400);
+        assert_eq!(TimeFormat::EpochMs.time_to_json(naive.time()), 44_055_500);
+    }
+
+    #[test]
+    fn test_duplicate_names() {
+        assert_eq!(first_duplicate(["a", "b", "c"]), None);
+        assert_eq!(first_duplicate(["a", "b", "b", "a"]), Some("b"));
+        assert_eq!(
+            suffixed_names(["a", "a", "b", "a", "a_2"]),
+            vec!["a", "a_3", "b", "a_4", "a_2"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_rows_to_arrays() -> anyhow::Result<()> {
+        let db_url = test_database_url();
+        let mut c = sqlx::AnyConnection::connect(&db_url).await?;
+        let rows = sqlx::query(
+            "SELECT 1 as b, 'x' as a, 2 as b \
+            UNION ALL SELECT 3, 'y', 4",
+        )
+        .fetch_all(&mut c)
+        .await?;
+        assert_eq!(
+            Decoder::new(DecodeOptions::default()).arrays(&rows),
+            vec![serde_json::json!([1, "x", 2]), serde_json::json!([3, "y", 4])]
+        );
+        assert_eq!(
+            Decoder::new(DecodeOptions::default()).column_arrays(&rows),
+            vec![
+                serde_json::json!([1, 3]),
+                serde_json::json!(["x", "y"]),
+                serde_json::json!([2, 4])
+            ]
+        );
+        Ok(())
+    }
+
     #[tokio::test] 
     async fn test_postgres_types() -> anyhow::Result<()> {
         let Some(db_url) = db_specific_test("postgres") else {
@@ -209,6 +949,17 @@ mod tests {
                 42::INT8 as big_int,
                 42.25::FLOAT4 as float4,
                 42.25::FLOAT8 as float8,
+                123.4500::NUMERIC(10,4) as numeric,
+                115792089237316195423570985008687907853269984665640564039457584007913129639935::NUMERIC(78,0) as uint256,
+                -0.00001234::NUMERIC as small_numeric,
+                'NaN'::NUMERIC as numeric_nan,
+                '\\xdeadbeef'::BYTEA as bytea,
+                ARRAY['0xddf2', NULL]::TEXT[] as topics,
+                ARRAY[0, 3]::INT8[] as trace_address,
+                ARRAY[[1, 2], [3, 4]]::INT4[] as matrix,
+                '{}'::INT4[] as empty_array,
+                ARRAY['{\"a\": 1}']::JSONB[] as jsonb_array,
+                ROW(1, 'x', ARRAY[1.5]::NUMERIC[]) as record,
                 TRUE as boolean,
                 '2024-03-14'::DATE as date,
                 '13:14:15'::TIME as time,
@@ -220,7 +971,13 @@ mod tests {
                 '{\"key\": \"value\"}'::JSON as json,
                 '{\"key\": \"value\"}'::JSONB as jsonb,
                 age('2024-03-14'::timestamp, '2024-01-01'::timestamp) as age_interval,
-                justify_interval(interval '1 year 2 months 3 days') as justified_interval",
+                justify_interval(interval '1 year 2 months 3 days') as justified_interval,
+                'A0EEBC99-9C0B-4EF8-BB6D-6BB9BD380A11'::UUID as uuid,
+                '192.168.0.1/24'::INET as inet,
+                '10.0.0.0/8'::CIDR as cidr,
+                '08-00-2b-01-02-03'::MACADDR as macaddr,
+                12.34::MONEY as money,
+                '<a>1</a>'::XML as xml",
         )
         .fetch_one(&mut c)
         .await?;
@@ -233,23 +990,111 @@ mod tests {
                 "big_int": 42,
                 "float4": 42.25,
                 "float8": 42.25,
+                "numeric": "123.4500",
+                "uint256": "115792089237316195423570985008687907853269984665640564039457584007913129639935",
+                "small_numeric": "-0.00001234",
+                "numeric_nan": "NaN",
+                "bytea": "0xdeadbeef",
+                "topics": ["0xddf2", null],
+                "trace_address": [0, 3],
+                "matrix": [[1, 2], [3, 4]],
+                "empty_array": [],
+                "jsonb_array": [{"a": 1}],
+                "record": {"f1": 1, "f2": "x", "f3": ["1.5"]},
                 "boolean": true,
                 "date": "2024-03-14",
                 "time": "13:14:15",
                 "timestamp": "2024-03-14T13:14:15+00:00",
                 "timestamptz": "2024-03-14T11:14:15+00:00",
-                "complex_interval": "1 year 2 mons 3 days",
-                "hour_interval": "04:00:00",
-                "fractional_interval": "1 day 12:00:00",
+                "complex_interval": {"iso": "P1Y2M3D", "months": 14, "days": 3, "microseconds": 0},
+                "hour_interval": {"iso": "PT4H", "months": 0, "days": 0, "microseconds": 14_400_000_000_i64},
+                "fractional_interval": {"iso": "P1DT12H", "months": 0, "days": 1, "microseconds": 43_200_000_000_i64},
                 "json": {"key": "value"},
                 "jsonb": {"key": "value"},
-                "age_interval": "2 mons 13 days",
-                "justified_interval": "1 year 2 mons 3 days"
+                "age_interval": {"iso": "P2M13D", "months": 2, "days": 13, "microseconds": 0},
+                "justified_interval": {"iso": "P1Y2M3D", "months": 14, "days": 3, "microseconds": 0},
+                "uuid": "a0eebc99-9c0b-4ef8-bb6d-6bb9bd380a11",
+                "inet": "192.168.0.1/24",
+                "cidr": "10.0.0.0/8",
+                "macaddr": "08:00:2b:01:02:03",
+                "money": "12.34",
+                "xml": "<a>1</a>"
             }),
         );
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_postgres_decimal_as_number() -> anyhow::Result<()> {
+        let Some(db_url) = db_specific_test("postgres") else {
+            return Ok(());
+        };
+        let mut c = sqlx::AnyConnection::connect(&db_url).await?;
+        let row = sqlx::query(
+            "SELECT
+                115792089237316195423570985008687907853269984665640564039457584007913129639935::NUMERIC(78,0) as uint256,
+                'NaN'::NUMERIC as numeric_nan",
+        )
+        .fetch_one(&mut c)
+        .await?;
+        let options = DecodeOptions {
+            decimal: DecimalFormat::Number,
+            ..DecodeOptions::default()
+        };
+        let rows = [row];
+        let indexed =
+            crate::encoding::IndexedRows::new(&rows, crate::encoding::Layout::Objects, options);
+        assert_eq!(
+            serde_json::to_string(&indexed)?,
+            r#"[{"uint256":115792089237316195423570985008687907853269984665640564039457584007913129639935,"numeric_nan":"NaN"}]"#
+        );
+        // As a `Value` the number has no exact form, so it keeps its digits as text
+        assert_eq!(
+            row_to_json_with(&rows[0], &options)["uint256"],
+            serde_json::json!(
+                "115792089237316195423570985008687907853269984665640564039457584007913129639935"
+            )
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_binary_format() {
+        assert_eq!(BinaryFormat::Hex.encode(&[0x00, 0xab, 0x10]), "0x00ab10");
+        assert_eq!(BinaryFormat::Hex.encode(&[]), "0x");
+        assert_eq!(BinaryFormat::Base64.encode(b"hello"), "aGVsbG8=");
+    }
+
+    #[test]
+    fn test_bigint_format() {
+        let unsafe_value = MAX_SAFE_INTEGER + 1;
+        assert_eq!(BigIntFormat::Number.to_json(unsafe_value), serde_json::json!(unsafe_value));
+        assert_eq!(BigIntFormat::String.to_json(42), serde_json::json!("42"));
+        assert_eq!(BigIntFormat::Auto.to_json(-MAX_SAFE_INTEGER), serde_json::json!(-MAX_SAFE_INTEGER));
+        assert_eq!(BigIntFormat::Auto.to_json(unsafe_value), serde_json::json!("9007199254740992"));
+        assert_eq!(BigIntFormat::Auto.to_json(u64::MAX), serde_json::json!("18446744073709551615"));
+    }
+
+    #[tokio::test]
+    async fn test_postgres_bigint_auto() -> anyhow::Result<()> {
+        let Some(db_url) = db_specific_test("postgres") else {
+            return Ok(());
+        };
+        let mut c = sqlx::AnyConnection::connect(&db_url).await?;
+        let row = sqlx::query("SELECT 9007199254740993::INT8 as big, 42::INT8 as small")
+            .fetch_one(&mut c)
+            .await?;
+        let options = DecodeOptions {
+            bigint: BigIntFormat::Auto,
+            ..DecodeOptions::default()
+        };
+        assert_eq!(
+            row_to_json_with(&row, &options),
+            serde_json::json!({"big": "9007199254740993", "small": 42})
+        );
+        Ok(())
+    }
+
     #[tokio::test] 
     async fn test_mysql_types() -> anyhow::Result<()> {
         let db_url = db_specific_test("mysql").or_else(|| db_specific_test("mariadb"));
@@ -327,7 +1172,7 @@ mod tests {
                 "small_int_unsigned": 65_535,
                 "medium_int_unsigned": 16_777_215,
                 "big_int_unsigned": 18_446_744_073_709_551_615_u64,
-                "decimal_num": 123.45,
+                "decimal_num": "123.45",
                 "float_num": 42.25,
                 "double_num": 42.25,
                 "bit_val": true,
@@ -369,12 +1214,45 @@ mod tests {
                 "integer": 42,
                 "real": 42.25,
                 "string": "xxx",
-                "blob": "hello world",
+                "blob": "0x68656c6c6f20776f726c64",
             }),
         );
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_decode_failures_are_reported() -> anyhow::Result<()> {
+        let Some(db_url) = db_specific_test("sqlite") else {
+            return Ok(());
+        };
+        let mut c = sqlx::AnyConnection::connect(&db_url).await?;
+        sqlx::query("CREATE TEMPORARY TABLE events (label TEXT, n INTEGER)")
+            .execute(&mut c)
+            .await?;
+        // SQLite stores whatever bytes it is given, so the second label is not UTF-8
+        sqlx::query("INSERT INTO events VALUES ('ok', 1), (CAST(X'FF' AS TEXT), 2)")
+            .execute(&mut c)
+            .await?;
+        let rows = sqlx::query("SELECT label, n FROM events ORDER BY n")
+            .fetch_all(&mut c)
+            .await?;
+
+        let mut decoder = Decoder::new(DecodeOptions::default());
+        assert_eq!(
+            decoder.arrays(&rows),
+            vec![serde_json::json!(["ok", 1]), serde_json::json!([null, 2])]
+        );
+        let warnings = decoder.into_warnings();
+        let [warning] = warnings.as_slice() else {
+            panic!("expected one warning, got {warnings:?}");
+        };
+        assert_eq!(warning.row, 1);
+        assert_eq!(warning.column, "label");
+        assert_eq!(warning.type_name, "TEXT");
+        assert!(!warning.error.is_empty());
+        Ok(())
+    }
+
     #[tokio::test] 
     async fn test_mssql_types() -> anyhow::Result<()> {
         let Some(db_url) = db_specific_test("mssql") else {
@@ -393,6 +1271,7 @@ mod tests {
                 CAST(42.25 AS REAL) as real,
                 CAST(42.25 AS FLOAT) as float,
                 CAST(42.25 AS DECIMAL(10,2)) as decimal,
+                CAST(12345.6789 AS MONEY) as money,
                 CAST('2024-03-14' AS DATE) as date,
                 CAST('13:14:15' AS TIME) as time,
                 CAST('2024-03-14 13:14:15' AS DATETIME) as datetime,
@@ -416,7 +1295,8 @@ mod tests {
                 "big_int": 42,
                 "real": 42.25,
                 "float": 42.25,
-                "decimal": 42.25,
+                "decimal": "42.25",
+                "money": "12345.6789",
                 "date": "2024-03-14",
                 "time": "13:14:15",
                 "datetime": "2024-03-14T13:14:15",
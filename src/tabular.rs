@@ -0,0 +1,367 @@
+use chrono::DateTime;
+use serde_json::{Map, Value};
+
+use crate::response::Column;
+use crate::sql_to_json::{BigIntFormat, DecodeOptions, TimeFormat};
+
+/// Interpreter results flattened into column metadata plus one object per row.
+#[derive(Debug, Default)]
+pub struct Table {
+    pub columns: Vec<Column>,
+    pub rows: Vec<Value>,
+}
+
+/// Flattens SuiQL/EQL results into a single table.
+///
+/// Each statement result nests its records as `{"result": {"<entity>": [ {...}, ... ]}}`.
+/// Records become rows, nested objects become dotted columns (`header.number`) and fields
+/// missing from a record are null. With several statements, `statement` and `entity`
+/// columns say where each row came from.
+pub fn normalize(results: &[Value]) -> Table {
+    let multi = results.len() > 1;
+    let mut names: Vec<String> = Vec::new();
+    let mut records: Vec<Map<String, Value>> = Vec::new();
+
+    for (statement, result) in results.iter().enumerate() {
+        let (entity, items) = find_records(result);
+        for item in items {
+            let mut record = Map::new();
+            if multi {
+                record.insert("statement".to_string(), statement.into());
+                record.insert("entity".to_string(), entity.clone().into());
+            }
+            match item {
+                Value::Object(fields) => flatten_into(&mut record, None, fields),
+                other => {
+                    record.insert("value".to_string(), other.clone());
+                }
+            }
+            for key in record.keys() {
+                if !names.contains(key) {
+                    names.push(key.clone());
+                }
+            }
+            records.push(record);
+        }
+    }
+
+    let columns = names
+        .iter()
+        .map(|name| {
+            let type_name = json_type(records.iter().filter_map(|r| r.get(name)));
+            // Columns that are null throughout could hold any JSON value
+            let logical_type = if type_name == "null" { "json" } else { type_name };
+            Column {
+                name: name.clone(),
+                type_name: type_name.to_string(),
+                logical_type: logical_type.to_string(),
+                nullable: None,
+                unsupported: false,
+            }
+        })
+        .collect();
+    let rows = records
+        .into_iter()
+        .map(|mut record| {
+            let mut row = Map::new();
+            for name in &names {
+                row.insert(name.clone(), record.remove(name).unwrap_or(Value::Null));
+            }
+            Value::Object(row)
+        })
+        .collect();
+    Table { columns, rows }
+}
+
+/// Row-major positional arrays following `columns`.
+pub fn to_arrays(columns: &[Column], rows: &[Value]) -> Vec<Value> {
+    rows.iter()
+        .map(|row| Value::Array(columns.iter().map(|c| row[&c.name].clone()).collect()))
+        .collect()
+}
+
+/// Column-major arrays, one per entry in `columns`.
+pub fn to_column_arrays(columns: &[Column], rows: &[Value]) -> Vec<Value> {
+    columns
+        .iter()
+        .map(|c| Value::Array(rows.iter().map(|row| row[&c.name].clone()).collect()))
+        .collect()
+}
+
+/// Rewrites the integer cells of `int64`/`uint64` columns according to `bigint`.
+pub fn encode_bigints(columns: &[Column], rows: &mut [Value], bigint: BigIntFormat) {
+    if bigint == BigIntFormat::Number {
+        return;
+    }
+    let integer_columns: Vec<&str> = columns
+        .iter()
+        .filter(|c| matches!(c.type_name.as_str(), "int64" | "uint64"))
+        .map(|c| c.name.as_str())
+        .collect();
+    for row in rows {
+        let Value::Object(row) = row else { continue };
+        for name in &integer_columns {
+            if let Some(Value::Number(n)) = row.get(*name) {
+                let n = n.clone();
+                row.insert(name.to_string(), bigint.to_json(n));
+            }
+        }
+    }
+}
+
+/// Milliseconds per unit of the epoch timestamps interpreter results carry, by field
+/// name: Sui's `timestamp_ms` and EVM blocks' `timestamp` seconds, also when nested
+/// (`header.timestamp`). The interpreters do not declare field types, so timestamps under
+/// other names are left as they are.
+fn epoch_unit(name: &str) -> Option<i64> {
+    match name.rsplit('.').next() {
+        Some("timestamp_ms") => Some(1),
+        Some("timestamp") => Some(1000),
+        _ => None,
+    }
+}
+
+/// Rewrites epoch timestamp columns (see [`epoch_unit`]) with `options.time_format` and
+/// `options.tz`, retyping the columns to match. Values may be numbers, decimal strings
+/// or `0x` hex strings; anything else is left as is. Returns whether any column was
+/// recognized as a timestamp.
+pub fn encode_timestamps(
+    columns: &mut [Column],
+    rows: &mut [Value],
+    options: &DecodeOptions,
+) -> bool {
+    let mut recognized = false;
+    for column in columns.iter_mut() {
+        let Some(millis_per_unit) = epoch_unit(&column.name) else {
+            continue;
+        };
+        recognized = true;
+        for row in rows.iter_mut() {
+            let Some(cell) = row.get_mut(column.name.as_str()) else {
+                continue;
+            };
+            let epoch = match cell {
+                Value::Number(n) => n.as_i64(),
+                Value::String(s) => match s.strip_prefix("0x") {
+                    Some(hex) => i64::from_str_radix(hex, 16).ok(),
+                    None => s.parse().ok(),
+                },
+                _ => None,
+            };
+            let instant = epoch
+                .and_then(|epoch| epoch.checked_mul(millis_per_unit))
+                .and_then(DateTime::from_timestamp_millis);
+            if let Some(instant) = instant {
+                *cell = options
+                    .time_format
+                    .instant_to_json(instant.fixed_offset(), options.tz);
+            }
+        }
+        let type_name = json_type(rows.iter().filter_map(|r| r.get(&column.name)));
+        column.type_name = type_name.to_string();
+        column.logical_type = match type_name {
+            "text" if options.time_format == TimeFormat::Rfc3339 => "timestamptz",
+            "null" => "json",
+            other => other,
+        }
+        .to_string();
+    }
+    recognized
+}
+
+/// Walks down single-key objects (`result` -> `account`) until it reaches the record list.
+fn find_records(result: &Value) -> (String, Vec<&Value>) {
+    let mut entity = String::new();
+    let mut current = result;
+    loop {
+        match current {
+            Value::Object(map) if map.len() == 1 => {
+                let (key, value) = map.iter().next().unwrap();
+                if key != "result" {
+                    entity = key.clone();
+                }
+                current = value;
+            }
+            Value::Array(items) => return (entity, items.iter().collect()),
+            Value::Null => return (entity, Vec::new()),
+            other => return (entity, vec![other]),
+        }
+    }
+}
+
+fn flatten_into(record: &mut Map<String, Value>, prefix: Option<&str>, fields: &Map<String, Value>) {
+    for (key, value) in fields {
+        let name = match prefix {
+            Some(prefix) => format!("{}.{}", prefix, key),
+            None => key.clone(),
+        };
+        match value {
+            Value::Object(nested) if !nested.is_empty() => flatten_into(record, Some(&name), nested),
+            other => {
+                record.insert(name, other.clone());
+            }
+        }
+    }
+}
+
+/// Column type from its values, using the same JSON typing `sql_to_json` produces:
+/// integers, floats, booleans and text stay scalar, anything nested is `json`.
+pub fn json_type<'a>(values: impl Iterator<Item = &'a Value>) -> &'static str {
+    let (mut ints, mut negative, mut big, mut floats) = (false, false, false, false);
+    let mut other: Option<&'static str> = None;
+    let mut mixed = false;
+    for value in values {
+        let kind = match value {
+            Value::Null => continue,
+            Value::Number(n) => {
+                if n.is_i64() {
+                    ints = true;
+                    negative |= n.as_i64().is_some_and(|v| v < 0);
+                } else if n.is_u64() {
+                    big = true;
+                } else {
+                    floats = true;
+                }
+                continue;
+            }
+            Value::Bool(_) => "bool",
+            Value::String(_) => "text",
+            Value::Array(_) | Value::Object(_) => "json",
+        };
+        mixed |= other.is_some_and(|o| o != kind);
+        other = Some(kind);
+    }
+    let numeric = ints || big || floats;
+    match (other, numeric) {
+        (None, false) => "null",
+        (Some(kind), false) if !mixed => kind,
+        (None, true) if floats || (big && negative) => "float",
+        (None, true) if big => "uint64",
+        (None, true) => "int64",
+        _ => "json",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_normalize_single_statement() {
+        let results = vec![json!({
+            "result": {
+                "account": [
+                    {"address": "0xabc", "nonce": 1, "balance": "100", "code": null},
+                    {"address": "0xdef", "nonce": 2, "ens": {"name": "x.eth"}}
+                ]
+            }
+        })];
+        let table = normalize(&results);
+        let names: Vec<&str> = table.columns.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, vec!["address", "nonce", "balance", "code", "ens.name"]);
+        let types: Vec<&str> = table.columns.iter().map(|c| c.type_name.as_str()).collect();
+        assert_eq!(types, vec!["text", "int64", "text", "null", "text"]);
+        assert_eq!(table.rows[1]["balance"], Value::Null);
+        assert_eq!(table.rows[1]["ens.name"], "x.eth");
+    }
+
+    #[test]
+    fn test_normalize_multiple_statements() {
+        let results = vec![
+            json!({"result": {"block": [{"number": 1}]}}),
+            json!({"result": {"transaction": [{"hash": "0x1"}]}}),
+        ];
+        let table = normalize(&results);
+        assert_eq!(table.rows.len(), 2);
+        assert_eq!(table.rows[0]["entity"], "block");
+        assert_eq!(table.rows[1]["statement"], 1);
+        assert_eq!(table.rows[1]["number"], Value::Null);
+    }
+
+    #[test]
+    fn test_columnar_layouts() {
+        let results = vec![json!({"result": {"block": [{"number": 1, "hash": "0x1"}, {"number": 2}]}})];
+        let table = normalize(&results);
+        assert_eq!(
+            to_arrays(&table.columns, &table.rows),
+            vec![json!([1, "0x1"]), json!([2, null])]
+        );
+        assert_eq!(
+            to_column_arrays(&table.columns, &table.rows),
+            vec![json!([1, 2]), json!(["0x1", null])]
+        );
+    }
+
+    #[test]
+    fn test_encode_bigints() {
+        let results = vec![json!({"result": {"block": [
+            {"number": 1, "timestamp_ms": 9_007_199_254_740_993_u64, "gas": 2.5},
+            {"number": 2, "timestamp_ms": 3, "gas": 1},
+        ]}})];
+        let mut table = normalize(&results);
+        encode_bigints(&table.columns, &mut table.rows, BigIntFormat::Auto);
+        assert_eq!(table.rows[0]["timestamp_ms"], "9007199254740993");
+        assert_eq!(table.rows[1]["timestamp_ms"], 3);
+        encode_bigints(&table.columns, &mut table.rows, BigIntFormat::String);
+        assert_eq!(table.rows[1]["number"], "2");
+        assert_eq!(table.rows[1]["gas"], 1);
+    }
+
+    #[test]
+    fn test_encode_timestamps() {
+        let results = vec![json!({"result": {"checkpoint": [
+            {"sequence_number": 1, "timestamp_ms": "1710422055123"},
+            {"sequence_number": 2, "timestamp_ms": null},
+        ]}})];
+        let mut table = normalize(&results);
+        let options = DecodeOptions {
+            tz: Some(chrono_tz::Asia::Tokyo),
+            ..DecodeOptions::default()
+        };
+        assert!(encode_timestamps(
+            &mut table.columns,
+            &mut table.rows,
+            &options
+        ));
+        assert_eq!(
+            table.rows[0]["timestamp_ms"],
+            "2024-03-14T22:14:15.123+09:00"
+        );
+        assert_eq!(table.rows[1]["timestamp_ms"], Value::Null);
+        assert_eq!(table.columns[1].logical_type, "timestamptz");
+        assert_eq!(table.rows[0]["sequence_number"], 1);
+
+        let results = vec![json!({"result": {"block": [{"timestamp": "0x65f2fd27"}]}})];
+        let mut table = normalize(&results);
+        let options = DecodeOptions {
+            time_format: TimeFormat::EpochMs,
+            ..DecodeOptions::default()
+        };
+        encode_timestamps(&mut table.columns, &mut table.rows, &options);
+        assert_eq!(table.rows[0]["timestamp"], 1_710_423_335_000_i64);
+        assert_eq!(table.columns[0].type_name, "int64");
+
+        let results = vec![json!({"result": {"block": [{"time": 1_710_423_335}]}})];
+        let mut table = normalize(&results);
+        assert!(!encode_timestamps(
+            &mut table.columns,
+            &mut table.rows,
+            &options
+        ));
+        assert_eq!(table.rows[0]["time"], 1_710_423_335);
+    }
+
+    #[test]
+    fn test_json_type_widening() {
+        let values = [json!(1), json!(2.5)];
+        assert_eq!(json_type(values.iter()), "float");
+        let values = [json!(1), json!("x")];
+        assert_eq!(json_type(values.iter()), "json");
+        let values = [json!(1), json!(u64::MAX)];
+        assert_eq!(json_type(values.iter()), "uint64");
+        let values = [json!(-1), json!(u64::MAX)];
+        assert_eq!(json_type(values.iter()), "float");
+        assert_eq!(json_type([Value::Null].iter()), "null");
+    }
+}
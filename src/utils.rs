@@ -6,7 +6,9 @@ use rocket::{
 
 use serde::Serialize;
 use serde_json::json;
-use std:: collections::HashSet;
+use utoipa::ToSchema;
+
+use crate::chains::{ChainFamily, ChainRegistry, CHAIN_TABLE};
 
 
 pub fn remove_sql_comments(sql: &str) -> String {
@@ -98,48 +100,52 @@ pub fn remove_sql_comments(sql: &str) -> String {
         "datestyle", "timezone",
 
         // Extensions or plugins
-        "plpgsql", "pgcrypto", "postgis", "pgstattuple", "snowball", "tsearch2", "uuid-ossp", "xml2","size"
+        "plpgsql", "pgcrypto", "postgis", "pgstattuple", "snowball", "tsearch2", "uuid-ossp", "xml2","size",
+
+        // Server-owned tables
+        "sandworm_abis"
     ];
-    
-pub fn is_query_only(sql: String) -> bool { 
-    !is_blacklisted_query(&sql)
-}
 
-fn is_blacklisted_query(sql: &str) -> bool {
-    const BLACKLIST_REGEX: &str = r###"(?i)\b(INSERT|UPDATE|DELETE|MERGE|UPSERT|TRUNCATE|RETURNING|OVERRIDING\s+SYSTEM\s+VALUE|CREATE|ALTER|DROP|RENAME|COMMENT|REINDEX|CLUSTER|DISCARD|BEGIN|COMMIT|ROLLBACK|SAVEPOINT|RELEASE|PREPARE|DEALLOCATE|GRANT|REVOKE|CREATE\s+USER|DROP\s+USER|CREATE\s+ROLE|DROP\s+ROLE|ALTER\s+USER|ALTER\s+ROLE|SET\s+ROLE|RESET\s+ROLE|SESSION\s+AUTHORIZATION|SET\s+SESSION\s+AUTHORIZATION|LOGIN|PASSWORD|CREATE\s+TABLE|DROP\s+TABLE|ALTER\s+TABLE|UNLOGGED|TEMP\s+TABLE|TEMPORARY|CREATE\s+SEQUENCE|ALTER\s+SEQUENCE|DROP\s+SEQUENCE|CREATE\s+VIEW|DROP\s+VIEW|ALTER\s+VIEW|MATERIALIZED|REFRESH\s+MATERIALIZED\s+VIEW|CREATE\s+FUNCTION|ALTER\s+FUNCTION|DROP\s+FUNCTION|CREATE\s+PROCEDURE|DROP\s+PROCEDURE|CALL|CREATE\s+TRIGGER|DROP\s+TRIGGER|CREATE\s+RULE|DROP\s+RULE|CREATE\s+INDEX|DROP\s+INDEX|USING\s+BTREE|USING\s+GIN|USING\s+HASH|CREATE\s+EXTENSION|ALTER\s+EXTENSION|DROP\s+EXTENSION|CREATE\s+SCHEMA|DROP\s+SCHEMA|ALTER\s+SCHEMA|COPY|DO|LISTEN|NOTIFY|UNLISTEN|EXPLAIN|ANALYZE|SHOW|SET|RESET|CONFIG|LOAD|VACUUM|CHECKPOINT|REASSIGN\s+OWNED|pg_sleep|pg_cancel_backend|pg_terminate_backend|pg_reload_conf|pg_rotate_logfile|pg_stat_reset|pg_logical_emit_message|pg_backend_pid|pg_postmaster_start_time|pg_current_xact_id|txid_current|pg_is_in_recovery|pg_last_xact_replay_timestamp|pg_switch_wal|pg_create_physical_replication_slot|pg_drop_replication_slot|pg_create_logical_replication_slot|pg_drop_logical_replication_slot|pg_current_wal_lsn|pg_wal_lsn_diff|pg_replication_origin|pg_create_restore_point|pg_start_backup|pg_stop_backup|pg_promote|pg_stat_|pg_replication_|pg_settings|pg_file_|pg_ls_|pg_log_|pg_read_file|pg_read_binary_file|pg_stat_file|pg_tablespace|pg_database|pg_user|pg_roles|pg_shadow|pg_authid|pg_auth_members|pg_group|pg_size_pretty|pg_table_size|pg_database_size|pg_indexes_size|pg_total_relation_size|pg_column_size|pg_relation_size|inet_client_addr|inet_client_port|inet_server_addr|inet_server_port|pg_hba_file_rules|pg_ident_file_mappings|--|/\*|\*/|#|;|;--|OR\s+1=1|' OR '1'='1|\" OR \"1\"=\"1|UNION\s+SELECT|INFORMATION_SCHEMA|SYSTEM_USER|CURRENT_CATALOG|CURRENT_SCHEMA|client_encoding|application_name|standard_conforming_strings|statement_timeout|idle_in_transaction_session_timeout|log_min_duration_statement|work_mem|maintenance_work_mem|shared_buffers|effective_cache_size|user|current_user|session_user|system_user|is_superuser|datestyle|timezone|plpgsql|pgcrypto|postgis|pgstattuple|snowball|tsearch2|uuid-ossp|xml2|size)\b"###;
+/// Returns the first blacklisted keyword or pattern in `sql` along with its byte offset.
+pub fn find_blacklisted(sql: &str) -> Option<(usize, &str)> {
+    const BLACKLIST_REGEX: &str = r###"(?i)\b(INSERT|UPDATE|DELETE|MERGE|UPSERT|TRUNCATE|RETURNING|OVERRIDING\s+SYSTEM\s+VALUE|CREATE|ALTER|DROP|RENAME|COMMENT|REINDEX|CLUSTER|DISCARD|BEGIN|COMMIT|ROLLBACK|SAVEPOINT|RELEASE|PREPARE|DEALLOCATE|GRANT|REVOKE|CREATE\s+USER|DROP\s+USER|CREATE\s+ROLE|DROP\s+ROLE|ALTER\s+USER|ALTER\s+ROLE|SET\s+ROLE|RESET\s+ROLE|SESSION\s+AUTHORIZATION|SET\s+SESSION\s+AUTHORIZATION|LOGIN|PASSWORD|CREATE\s+TABLE|DROP\s+TABLE|ALTER\s+TABLE|UNLOGGED|TEMP\s+TABLE|TEMPORARY|CREATE\s+SEQUENCE|ALTER\s+SEQUENCE|DROP\s+SEQUENCE|CREATE\s+VIEW|DROP\s+VIEW|ALTER\s+VIEW|MATERIALIZED|REFRESH\s+MATERIALIZED\s+VIEW|CREATE\s+FUNCTION|ALTER\s+FUNCTION|DROP\s+FUNCTION|CREATE\s+PROCEDURE|DROP\s+PROCEDURE|CALL|CREATE\s+TRIGGER|DROP\s+TRIGGER|CREATE\s+RULE|DROP\s+RULE|CREATE\s+INDEX|DROP\s+INDEX|USING\s+BTREE|USING\s+GIN|USING\s+HASH|CREATE\s+EXTENSION|ALTER\s+EXTENSION|DROP\s+EXTENSION|CREATE\s+SCHEMA|DROP\s+SCHEMA|ALTER\s+SCHEMA|COPY|DO|LISTEN|NOTIFY|UNLISTEN|EXPLAIN|ANALYZE|SHOW|SET|RESET|CONFIG|LOAD|VACUUM|CHECKPOINT|REASSIGN\s+OWNED|pg_sleep|pg_cancel_backend|pg_terminate_backend|pg_reload_conf|pg_rotate_logfile|pg_stat_reset|pg_logical_emit_message|pg_backend_pid|pg_postmaster_start_time|pg_current_xact_id|txid_current|pg_is_in_recovery|pg_last_xact_replay_timestamp|pg_switch_wal|pg_create_physical_replication_slot|pg_drop_replication_slot|pg_create_logical_replication_slot|pg_drop_logical_replication_slot|pg_current_wal_lsn|pg_wal_lsn_diff|pg_replication_origin|pg_create_restore_point|pg_start_backup|pg_stop_backup|pg_promote|pg_stat_|pg_replication_|pg_settings|pg_file_|pg_ls_|pg_log_|pg_read_file|pg_read_binary_file|pg_stat_file|pg_tablespace|pg_database|pg_user|pg_roles|pg_shadow|pg_authid|pg_auth_members|pg_group|pg_size_pretty|pg_table_size|pg_database_size|pg_indexes_size|pg_total_relation_size|pg_column_size|pg_relation_size|inet_client_addr|inet_client_port|inet_server_addr|inet_server_port|pg_hba_file_rules|pg_ident_file_mappings|--|/\*|\*/|#|;|;--|OR\s+1=1|' OR '1'='1|\" OR \"1\"=\"1|UNION\s+SELECT|INFORMATION_SCHEMA|SYSTEM_USER|CURRENT_CATALOG|CURRENT_SCHEMA|client_encoding|application_name|standard_conforming_strings|statement_timeout|idle_in_transaction_session_timeout|log_min_duration_statement|work_mem|maintenance_work_mem|shared_buffers|effective_cache_size|user|current_user|session_user|system_user|is_superuser|datestyle|timezone|plpgsql|pgcrypto|postgis|pgstattuple|snowball|tsearch2|uuid-ossp|xml2|size|sandworm_abis)\b"###;
     let re = Regex::new(BLACKLIST_REGEX).unwrap();
-    re.is_match(sql)
+    re.find(sql).map(|m| (m.start(), m.as_str()))
 }
 
-pub fn is_sui_rpc_query(query: &str) -> bool {
-    let upper = query.to_uppercase();
-    ["SUI", "SUITEST", "SUIDEV"]
+pub fn is_sui_rpc_query(query: &str, chains: &ChainRegistry) -> bool {
+    chains
+        .referenced_chains(query)
         .iter()
-        .any(|target| upper.contains(target))
+        .any(|chain| chain.family == ChainFamily::Sui && chain.rpc())
 }
 
-pub fn flatten_known_chain_tables(sql: &str) -> String {
-    let known_chains: HashSet<&'static str> = [
-        "sui", "suidev", "suitest", // Non-EVM
-        "eth", "sepolia", "arb", "base", "blast", "op", "poly", "mycelium", "mnt", "zks", "taiko",
-        "celo", "avax", "scroll", "bnb", "linea", "zora", "glmr", "movr", "ron", "ftm", "kava",
-        "gno", "mekong", "mina",
-    ]
-    .into_iter()
-    .collect();
-
-    let re = Regex::new(r"\b([a-zA-Z0-9_]+)\.([a-zA-Z0-9_]+)\b").unwrap();
-
-    re.replace_all(sql, |caps: &regex::Captures| {
-        let chain = &caps[1];
-        let table = &caps[2];
-        if known_chains.contains(chain) {
-            format!("{}_{}", chain, table)
-        } else {
-            caps[0].to_string() // Leave it untouched
+/// Every `chain.table` reference in `sql` that flattening would rewrite, paired with its
+/// physical table name, e.g. `("arbitrum.logs", "arb_logs")`.
+pub fn resolve_chain_tables(sql: &str, chains: &ChainRegistry) -> Vec<(String, String)> {
+    let mut resolved: Vec<(String, String)> = Vec::new();
+    for caps in CHAIN_TABLE.captures_iter(sql) {
+        let Some(chain) = chains.get(&caps[1]).filter(|chain| chain.indexed()) else {
+            continue;
+        };
+        let entry = (caps[0].to_string(), format!("{}_{}", chain.id, &caps[2]));
+        if !resolved.contains(&entry) {
+            resolved.push(entry);
         }
-    })
-    .to_string()
+    }
+    resolved
+}
+
+pub fn flatten_known_chain_tables(sql: &str, chains: &ChainRegistry) -> String {
+    CHAIN_TABLE
+        .replace_all(sql, |caps: &regex::Captures| {
+            let table = &caps[2];
+            match chains.get(&caps[1]) {
+                Some(chain) if chain.indexed() => format!("{}_{}", chain.id, table),
+                _ => caps[0].to_string(), // Leave it untouched
+            }
+        })
+        .to_string()
 }
 
 pub fn json_response<T: Serialize>(status: Status, data: T) -> status::Custom<RawJson<String>> {
@@ -148,17 +154,24 @@ pub fn json_response<T: Serialize>(status: Status, data: T) -> status::Custom<Ra
     status::Custom(status, RawJson(body))
 }
 
+#[derive(Serialize, ToSchema)]
+pub struct ErrorResponse {
+    pub error: String,
+}
+
 pub fn json_error<E: ToString>(err: E) -> status::Custom<RawJson<String>> {
-    let err = err.to_string();
     json_response(
         Status::InternalServerError,
-        json!({ "error": format!("{}", err.to_string()) }),
+        ErrorResponse {
+            error: err.to_string(),
+        },
     )
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::utils::is_query_only;
+    use crate::chains::ChainRegistry;
+    use crate::utils::{find_blacklisted, flatten_known_chain_tables, is_sui_rpc_query};
 
     use super::remove_sql_comments;
 
@@ -194,51 +207,75 @@ mod tests {
     }
 
     #[test]
-    fn test_query_only_sql() {
+    fn test_select_is_not_blacklisted() {
         let query = "SELECT * FROM users WHERE id = 1";
-        assert!(is_query_only(query.to_string()));
+        assert!(find_blacklisted(query).is_none());
     }
 
     #[test]
-    fn test_insert_sql_is_not_query_only() {
+    fn test_insert_is_blacklisted() {
         let query = "INSERT INTO users (name) VALUES ('Alice')";
-        assert!(!is_query_only(query.to_string()));
+        assert!(find_blacklisted(query).is_some());
     }
 
     #[test]
-    fn test_update_sql_is_not_query_only() {
+    fn test_update_is_blacklisted() {
         let query = "UPDATE users SET name = 'Bob' WHERE id = 1";
-        assert!(!is_query_only(query.to_string()));
+        assert!(find_blacklisted(query).is_some());
     }
 
     #[test]
-    fn test_dangerous_function_call_is_not_query_only() {
+    fn test_dangerous_function_call_is_blacklisted() {
         let query = "SELECT pg_sleep(10)";
-        assert!(!is_query_only(query.to_string()));
+        assert!(find_blacklisted(query).is_some());
     }
 
     #[test]
     fn test_safe_uppercase_select_query() {
         let query = "SELECT name FROM USERS";
-        assert!(is_query_only(query.to_string()));
+        assert!(find_blacklisted(query).is_none());
     }
 
     #[test]
     fn test_sql_injection_pattern() {
         let query = "' OR '1'='1";
-        assert!(!is_query_only(query.to_string()));
+        assert!(find_blacklisted(query).is_some());
+    }
+
+    #[test]
+    fn test_server_tables_are_blacklisted() {
+        let query = "SELECT abi FROM sandworm_internal.sandworm_abis";
+        assert!(find_blacklisted(query).is_some());
     }
 
     #[test]
     fn test_union_select_attack() {
         let query = "UNION SELECT password FROM users";
-        assert!(!is_query_only(query.to_string()));
+        assert!(find_blacklisted(query).is_some());
+    }
+
+    #[test]
+    fn test_flatten_known_chain_tables() {
+        let chains = ChainRegistry::builtin();
+        let sql = "SELECT * FROM eth.blocks b JOIN arbitrum.logs l ON b.number = l.block_number";
+        assert_eq!(
+            flatten_known_chain_tables(sql, &chains),
+            "SELECT * FROM eth_blocks b JOIN arb_logs l ON b.number = l.block_number"
+        );
+    }
+
+    #[test]
+    fn test_sui_rpc_query_detection() {
+        let chains = ChainRegistry::builtin();
+        assert!(is_sui_rpc_query("GET * FROM object 0x5 ON suitest", &chains));
+        assert!(!is_sui_rpc_query("GET balance FROM account 0x0 ON eth", &chains));
+        assert!(!is_sui_rpc_query("GET sui FROM account 0x0 ON eth", &chains));
     }
 
     #[test]
     fn test_with_comment_injection() {
         let query = "SELECT * FROM users; -- drop table users;";
-        assert!(!is_query_only(query.to_string()));
+        assert!(find_blacklisted(query).is_some());
     }
 
 }